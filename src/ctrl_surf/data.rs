@@ -1,10 +1,15 @@
 use std::{fmt, io::Write, sync::Arc, time::Duration};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Track {
+    /// Track url, or MPRIS track id if not available.
+    pub id: Option<Arc<str>>,
     pub artist: Option<Arc<str>>,
     pub album: Option<Arc<str>>,
     pub title: Option<Arc<str>>,
+    pub number: Option<u32>,
+    /// Index of the track in the player's track list.
+    pub list_index: Option<usize>,
     pub duration: Option<Duration>,
     pub image_url: Option<Arc<str>>,
 }
@@ -98,3 +103,51 @@ impl PlaybackStatus {
         matches!(self, Self::Playing)
     }
 }
+
+/// A-B loop markers on the current track.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Markers {
+    pub a: Option<Duration>,
+    pub b: Option<Duration>,
+}
+
+impl Markers {
+    /// Sets the next marker at `pos`: A, then B, then clears both.
+    pub fn set_next(&mut self, pos: Duration) {
+        match (self.a, self.b) {
+            (None, _) => self.a = Some(pos),
+            (Some(a), None) if pos > a => self.b = Some(pos),
+            // B can't be before A, so start over.
+            (Some(_), None) => self.a = Some(pos),
+            (Some(_), Some(_)) => *self = Self::default(),
+        }
+    }
+
+    /// Returns the A-B range if both markers are set.
+    pub fn range(&self) -> Option<(Duration, Duration)> {
+        Some((self.a?, self.b?))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.a.is_none() && self.b.is_none()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoopStatus {
+    #[default]
+    None,
+    Track,
+    Playlist,
+}
+
+impl LoopStatus {
+    pub fn next(self) -> Self {
+        use LoopStatus::*;
+        match self {
+            None => Track,
+            Track => Playlist,
+            Playlist => None,
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::sync::{Arc, Mutex};
+
+use crate::ctrl_surf::{
+    protocol::{Generic, Mmc, Mtc},
+    Mapping,
+};
+
+pub const GENERIC_MIDI: &str = "Generic MIDI (user mapping)";
+pub const MMC: &str = "MIDI Machine Control (MMC)";
+pub const MTC: &str = "MIDI Timecode (MTC) output";
+
+/// Plain MIDI device driven by the user mapping file.
+pub struct GenericMidi;
+
+impl crate::ctrl_surf::Buildable for GenericMidi {
+    const NAME: &'static str = GENERIC_MIDI;
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        let mapping = Mapping::load().unwrap_or_else(|err| {
+            log::warn!("Couldn't load {}: {err}", Mapping::path().display());
+            Mapping::default()
+        });
+
+        Arc::new(Mutex::new(Generic::new(mapping)))
+    }
+}
+
+/// Device sending & following MIDI Machine Control commands.
+pub struct MidiMachineControl;
+
+impl crate::ctrl_surf::Buildable for MidiMachineControl {
+    const NAME: &'static str = MMC;
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        Arc::new(Mutex::new(Mmc::default()))
+    }
+}
+
+/// MIDI Timecode generator following the current player.
+pub struct MidiTimecode;
+
+impl crate::ctrl_surf::Buildable for MidiTimecode {
+    const NAME: &'static str = MTC;
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        Arc::new(Mutex::new(Mtc::default()))
+    }
+}
@@ -0,0 +1,14 @@
+use std::sync::{Arc, Mutex};
+
+use crate::ctrl_surf::protocol::NanoKontrol2;
+
+/// Korg nanoKONTROL2 with the factory scene, LEDs driven by the app.
+pub struct KorgNanoKontrol2;
+
+impl crate::ctrl_surf::Buildable for KorgNanoKontrol2 {
+    const NAME: &'static str = "Korg nanoKONTROL2";
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        Arc::new(Mutex::new(NanoKontrol2::default()))
+    }
+}
@@ -0,0 +1,65 @@
+use std::sync::{Arc, Mutex};
+
+use crate::ctrl_surf::protocol::Mackie;
+
+pub struct McuPro;
+
+impl crate::ctrl_surf::Buildable for McuPro {
+    const NAME: &'static str = "Mackie Control Universal Pro";
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        Arc::new(Mutex::new(Mackie::new(Mackie::MCU_ID)))
+    }
+}
+
+pub struct McuXt;
+
+impl crate::ctrl_surf::Buildable for McuXt {
+    const NAME: &'static str = "Mackie Control Universal XT";
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        Arc::new(Mutex::new(Mackie::new(Mackie::MCU_EXT_ID)))
+    }
+}
+
+pub struct LogicControl;
+
+impl crate::ctrl_surf::Buildable for LogicControl {
+    const NAME: &'static str = "Logic Control";
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        Arc::new(Mutex::new(Mackie::new(Mackie::LOGIC_CONTROL_ID)))
+    }
+}
+
+pub struct LogicControlXt;
+
+impl crate::ctrl_surf::Buildable for LogicControlXt {
+    const NAME: &'static str = "Logic Control XT";
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        Arc::new(Mutex::new(Mackie::new(Mackie::LOGIC_CONTROL_EXT_ID)))
+    }
+}
+
+/// Icon Platform M+ in Mackie Control mode.
+pub struct IconPlatformMPlus;
+
+impl crate::ctrl_surf::Buildable for IconPlatformMPlus {
+    const NAME: &'static str = "Icon Platform M+ (Mackie)";
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        Arc::new(Mutex::new(Mackie::new(Mackie::MCU_ID)))
+    }
+}
+
+/// Mackie Control compatible device using one of the Logic Control or MCU device ids.
+pub struct GenericMackie;
+
+impl crate::ctrl_surf::Buildable for GenericMackie {
+    const NAME: &'static str = "Generic Mackie (Logic Control or MCU id)";
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        Arc::new(Mutex::new(Mackie::new_generic()))
+    }
+}
@@ -1,2 +1,14 @@
+mod generic;
+pub use generic::*;
+
+mod korg;
+pub use korg::*;
+
+mod mcu;
+pub use mcu::*;
+
+mod osc;
+pub use osc::*;
+
 mod xtouch;
 pub use xtouch::*;
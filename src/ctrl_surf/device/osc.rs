@@ -0,0 +1,16 @@
+use std::sync::{Arc, Mutex};
+
+use crate::ctrl_surf::protocol::Osc;
+
+pub const OSC: &str = "OSC (UDP)";
+
+/// OSC clients such as TouchOSC or Open Stage Control.
+pub struct OscUdp;
+
+impl crate::ctrl_surf::Buildable for OscUdp {
+    const NAME: &'static str = OSC;
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        Arc::new(Mutex::new(Osc::default()))
+    }
+}
@@ -1,9 +1,199 @@
 use std::sync::{Arc, Mutex};
 
-use crate::ctrl_surf::protocol::Mackie;
+use crate::{
+    ctrl_surf::{
+        event::{Data, Mixer, Transport},
+        protocol::{Composite, Hui, Layer, Layered, Mackie, XTouchMini},
+        AppEvent, Msg, PlaybackStatus,
+    },
+    midi,
+};
 
-pub const XTOUCH_ID: u8 = 0x14;
-pub const XTOUCH_EXT_ID: u8 = 0x15;
+pub const XTOUCH_ID: u8 = Mackie::MCU_ID;
+pub const XTOUCH_EXT_ID: u8 = Mackie::MCU_EXT_ID;
+
+pub const XTOUCH_STRIPS: usize = 8;
+
+mod color {
+    pub const OFF: u8 = 0;
+    pub const RED: u8 = 1;
+    pub const GREEN: u8 = 2;
+    pub const YELLOW: u8 = 3;
+    pub const CYAN: u8 = 6;
+    pub const WHITE: u8 = 7;
+}
+
+const SET_COLORS: u8 = 0x72;
+
+/// Colors the scribble strips after the playback state.
+///
+/// Green when playing, yellow when paused, red when muted
+/// and white when no player is available.
+pub struct ScribbleColors {
+    device_id: u8,
+    /// Index of the player bound to the first strip in multi-strip mode.
+    strip_offset: Option<usize>,
+    /// Players bound to the strips in multi-strip mode.
+    strips: [Strip; XTOUCH_STRIPS],
+    player_index: Option<usize>,
+    status: Option<PlaybackStatus>,
+    is_muted: bool,
+    last_colors: Option<[u8; XTOUCH_STRIPS]>,
+}
+
+#[derive(Default)]
+struct Strip {
+    player: Option<Arc<str>>,
+    is_muted: bool,
+}
+
+impl ScribbleColors {
+    pub fn new(device_id: u8) -> Self {
+        Self {
+            device_id,
+            strip_offset: None,
+            strips: Default::default(),
+            player_index: None,
+            status: None,
+            is_muted: false,
+            last_colors: None,
+        }
+    }
+
+    /// Builds the colors for a multi-strip device.
+    ///
+    /// Only the current player's strip follows the playback state.
+    /// The strips bound to the other players are cyan, or red when muted,
+    /// and the strips without a player are white.
+    pub fn new_multi_strip(device_id: u8, strip_offset: usize) -> Self {
+        Self {
+            strip_offset: Some(strip_offset),
+            ..Self::new(device_id)
+        }
+    }
+
+    fn color(&self) -> u8 {
+        use PlaybackStatus::*;
+
+        if self.is_muted {
+            return color::RED;
+        }
+
+        match self.status {
+            Some(Playing) => color::GREEN,
+            Some(Paused) => color::YELLOW,
+            Some(Stopped) | None => color::WHITE,
+        }
+    }
+
+    fn colors(&self) -> [u8; XTOUCH_STRIPS] {
+        let color = self.color();
+
+        let strip_offset = match self.strip_offset {
+            Some(strip_offset) => strip_offset,
+            None => return [color; XTOUCH_STRIPS],
+        };
+
+        let cur_strip = self
+            .player_index
+            .and_then(|index| index.checked_sub(strip_offset));
+
+        std::array::from_fn(|index| {
+            let strip = &self.strips[index];
+            if Some(index) == cur_strip {
+                color
+            } else if strip.player.is_none() {
+                color::WHITE
+            } else if strip.is_muted {
+                color::RED
+            } else {
+                color::CYAN
+            }
+        })
+    }
+
+    fn strip_mute(&mut self, index: usize, is_muted: bool) {
+        let strip = self
+            .strip_offset
+            .and_then(|strip_offset| index.checked_sub(strip_offset))
+            .and_then(|index| self.strips.get_mut(index));
+        if let Some(strip) = strip {
+            strip.is_muted = is_muted;
+        }
+    }
+
+    fn player_list(&mut self, list: &[Arc<str>]) {
+        let strip_offset = match self.strip_offset {
+            Some(strip_offset) => strip_offset,
+            None => return,
+        };
+
+        for (index, strip) in self.strips.iter_mut().enumerate() {
+            let player = list.get(strip_offset + index);
+            if strip.player.as_ref() != player {
+                // Another player, its mute state will be received.
+                *strip = Strip {
+                    player: player.cloned(),
+                    is_muted: false,
+                };
+            }
+        }
+    }
+
+    fn update(&mut self) -> Vec<Msg> {
+        let colors = self.colors();
+        if self.last_colors == Some(colors) {
+            return Msg::none();
+        }
+
+        self.last_colors = Some(colors);
+
+        let mut payload = [color::OFF; 5 + XTOUCH_STRIPS];
+        payload[..=2].copy_from_slice(&Mackie::MACKIE_ID);
+        payload[3] = self.device_id;
+        payload[4] = SET_COLORS;
+        payload[5..].copy_from_slice(&colors);
+
+        midi::Msg::new_sysex(&payload).to_device().into()
+    }
+}
+
+impl Layer for ScribbleColors {
+    fn connected(&mut self) -> Vec<Msg> {
+        self.last_colors = None;
+        self.update()
+    }
+
+    fn event_from_app(&mut self, event: &AppEvent) -> Vec<Msg> {
+        match event {
+            AppEvent::Transport(Transport::Play) => self.status = Some(PlaybackStatus::Playing),
+            AppEvent::Transport(Transport::Pause) => self.status = Some(PlaybackStatus::Paused),
+            AppEvent::Transport(Transport::Stop) => self.status = Some(PlaybackStatus::Stopped),
+            AppEvent::Data(Data::PlaybackStatus(status)) => self.status = Some(*status),
+            AppEvent::Mixer(Mixer::Mute) => self.is_muted = true,
+            AppEvent::Mixer(Mixer::Unmute) => self.is_muted = false,
+            AppEvent::Data(Data::PlayerIndex(index)) => self.player_index = Some(*index),
+            AppEvent::Data(Data::PlayerList(list)) => self.player_list(list),
+            AppEvent::Strip(index, Mixer::Mute) => self.strip_mute(*index, true),
+            AppEvent::Strip(index, Mixer::Unmute) => self.strip_mute(*index, false),
+            AppEvent::NewApp(_) => {
+                self.status = Some(PlaybackStatus::Paused);
+                self.is_muted = false;
+            }
+            _ => return Msg::none(),
+        }
+
+        self.update()
+    }
+
+    fn reset(&mut self) -> Vec<Msg> {
+        self.player_index = None;
+        self.status = None;
+        self.is_muted = false;
+
+        self.update()
+    }
+}
 
 pub struct XTouchMackie;
 
@@ -11,7 +201,33 @@ impl crate::ctrl_surf::Buildable for XTouchMackie {
     const NAME: &'static str = "X-Touch (Mackie)";
 
     fn build() -> crate::ctrl_surf::ControlSurfaceArc {
-        Arc::new(Mutex::new(Mackie::new(XTOUCH_ID)))
+        Arc::new(Mutex::new(Layered::new(
+            Mackie::new(XTOUCH_ID),
+            ScribbleColors::new(XTOUCH_ID),
+        )))
+    }
+}
+
+pub struct XTouchMackieMultiStrip;
+
+impl crate::ctrl_surf::Buildable for XTouchMackieMultiStrip {
+    const NAME: &'static str = "X-Touch (Mackie, one strip per player)";
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        Arc::new(Mutex::new(Layered::new(
+            Mackie::new_multi_strip(XTOUCH_ID),
+            ScribbleColors::new_multi_strip(XTOUCH_ID, 0),
+        )))
+    }
+}
+
+pub struct XTouchHui;
+
+impl crate::ctrl_surf::Buildable for XTouchHui {
+    const NAME: &'static str = "X-Touch (HUI)";
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        Arc::new(Mutex::new(Hui::default()))
     }
 }
 
@@ -21,6 +237,56 @@ impl crate::ctrl_surf::Buildable for XTouchExtMackie {
     const NAME: &'static str = "X-Touch Extension (Mackie)";
 
     fn build() -> crate::ctrl_surf::ControlSurfaceArc {
-        Arc::new(Mutex::new(Mackie::new(XTOUCH_EXT_ID)))
+        Arc::new(Mutex::new(Layered::new(
+            Mackie::new(XTOUCH_EXT_ID),
+            ScribbleColors::new(XTOUCH_EXT_ID),
+        )))
+    }
+}
+
+pub struct XTouchExtMackieMultiStrip;
+
+impl crate::ctrl_surf::Buildable for XTouchExtMackieMultiStrip {
+    const NAME: &'static str = "X-Touch Extension (Mackie, one strip per player)";
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        Arc::new(Mutex::new(Layered::new(
+            Mackie::new_multi_strip(XTOUCH_EXT_ID),
+            ScribbleColors::new_multi_strip(XTOUCH_EXT_ID, 0),
+        )))
+    }
+}
+
+/// X-Touch and X-Touch Extension combined, with one strip per player.
+///
+/// The X-Touch uses the main port pair and the Extension the second one.
+pub struct XTouchWithExtMackie;
+
+impl crate::ctrl_surf::Buildable for XTouchWithExtMackie {
+    const NAME: &'static str = "X-Touch + Extension (Mackie, one strip per player)";
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        Arc::new(Mutex::new(
+            Composite::default()
+                .with(Layered::new(
+                    Mackie::new_multi_strip(XTOUCH_ID),
+                    ScribbleColors::new_multi_strip(XTOUCH_ID, 0),
+                ))
+                .with(Layered::new(
+                    Mackie::new_multi_strip(XTOUCH_EXT_ID).with_strip_offset(XTOUCH_STRIPS),
+                    ScribbleColors::new_multi_strip(XTOUCH_EXT_ID, XTOUCH_STRIPS),
+                )),
+        ))
+    }
+}
+
+/// X-Touch Mini in standard mode.
+pub struct XTouchMiniStandard;
+
+impl crate::ctrl_surf::Buildable for XTouchMiniStandard {
+    const NAME: &'static str = "X-Touch Mini (standard mode)";
+
+    fn build() -> crate::ctrl_surf::ControlSurfaceArc {
+        Arc::new(Mutex::new(XTouchMini::default()))
     }
 }
@@ -19,4 +19,7 @@ pub enum Error {
 
     #[error("Device reported connection error")]
     ConnectionError,
+
+    #[error("MIDI mapping file error: {}", .0)]
+    MappingFile(#[from] std::io::Error),
 }
@@ -4,13 +4,23 @@ use std::{sync::Arc, time::Duration};
 pub enum CtrlSurfEvent {
     Transport(Transport),
     Mixer(Mixer),
+    /// Mixer event for the player at the given index in the players list.
+    Strip(usize, Mixer),
+    /// Makes the player at the given index in the players list the current one.
+    SelectPlayer(usize),
+    /// Makes the player assigned to the given slot the current one.
+    SelectPlayerSlot(usize),
+    /// Sets the next A-B loop marker at the current position.
+    Marker,
     DataRequest,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum AppEvent {
     Transport(Transport),
     Mixer(Mixer),
+    /// Mixer event for the player at the given index in the players list.
+    Strip(usize, Mixer),
     Data(Data),
     NewApp(Arc<str>),
 }
@@ -23,9 +33,12 @@ pub enum Transport {
     Stop,
     Previous,
     Next,
-    StepForward,
-    StepBackward,
+    StepForward(u16),
+    StepBackward(u16),
     SetPosition(Duration),
+    SetRate(f64),
+    SetShuffle(bool),
+    SetLoopStatus(super::LoopStatus),
 }
 
 impl From<Transport> for CtrlSurfEvent {
@@ -40,11 +53,13 @@ impl From<Transport> for AppEvent {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Mixer {
     Volume(f64),
     Mute,
     Unmute,
+    /// Left / right balance in the range `-1.0..=1.0`.
+    Balance(f64),
 }
 
 impl From<Mixer> for CtrlSurfEvent {
@@ -59,11 +74,25 @@ impl From<Mixer> for AppEvent {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Data {
     Track(super::Track),
     Position(std::time::Duration),
     PlaybackStatus(super::PlaybackStatus),
+    Rate(f64),
+    Shuffle(bool),
+    LoopStatus(super::LoopStatus),
+    Markers(super::Markers),
+    /// Audio peak level in the range `0.0..=1.0`.
+    Level(f64),
+    /// Whether the player is muted at the system level rather than in the player.
+    SystemMuted(bool),
+    /// Index of the current player in the players list.
+    PlayerIndex(usize),
+    /// Slot of the current player, if any.
+    PlayerSlot(Option<usize>),
+    /// Names of the available players.
+    PlayerList(Vec<Arc<str>>),
 }
 
 impl From<Data> for AppEvent {
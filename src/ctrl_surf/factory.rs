@@ -5,8 +5,24 @@ use super::{device, ControlSurface};
 
 pub static FACTORY: Lazy<Arc<Factory>> = Lazy::new(|| {
     Factory::default()
+        .with::<device::GenericMackie>()
+        .with::<device::GenericMidi>()
+        .with::<device::KorgNanoKontrol2>()
+        .with::<device::IconPlatformMPlus>()
+        .with::<device::LogicControl>()
+        .with::<device::LogicControlXt>()
+        .with::<device::McuPro>()
+        .with::<device::McuXt>()
+        .with::<device::MidiMachineControl>()
+        .with::<device::MidiTimecode>()
+        .with::<device::OscUdp>()
         .with::<device::XTouchMackie>()
+        .with::<device::XTouchHui>()
+        .with::<device::XTouchMackieMultiStrip>()
         .with::<device::XTouchExtMackie>()
+        .with::<device::XTouchExtMackieMultiStrip>()
+        .with::<device::XTouchWithExtMackie>()
+        .with::<device::XTouchMiniStandard>()
         .into()
 });
 
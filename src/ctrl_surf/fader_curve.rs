@@ -0,0 +1,73 @@
+/// Response curve between a fader position and the app volume.
+///
+/// Both are normalized in the range `0.0..=1.0`. The curve is applied
+/// in both directions so that motorized faders land where the user left them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FaderCurve {
+    #[default]
+    Linear,
+    /// Power law reaching 10% of the volume at half travel.
+    AudioTaper,
+    /// Linear in dB from [`DB_FLOOR`] to 0 dB, the bottom position being silent.
+    Db,
+}
+
+/// Volume at the bottom of the dB curve before it drops to silence.
+pub const DB_FLOOR: f64 = -60.0;
+
+/// Exponent for which half travel leads to 10% of the volume.
+const AUDIO_TAPER_EXP: f64 = std::f64::consts::LOG2_10;
+
+impl FaderCurve {
+    pub const ALL: [FaderCurve; 3] = [FaderCurve::Linear, FaderCurve::AudioTaper, FaderCurve::Db];
+
+    pub fn name(self) -> &'static str {
+        use FaderCurve::*;
+        match self {
+            Linear => "Linear",
+            AudioTaper => "Audio taper",
+            Db => "dB",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|curve| curve.name() == name)
+    }
+
+    /// Converts the fader `pos` into a volume.
+    pub fn to_volume(self, pos: f64) -> f64 {
+        use FaderCurve::*;
+
+        let pos = pos.clamp(0.0, 1.0);
+        match self {
+            Linear => pos,
+            AudioTaper => pos.powf(AUDIO_TAPER_EXP),
+            Db => {
+                if pos < f64::EPSILON {
+                    return 0.0;
+                }
+
+                10f64.powf(DB_FLOOR * (1.0 - pos) / 20.0)
+            }
+        }
+    }
+
+    /// Converts the `vol` into a fader position.
+    pub fn to_position(self, vol: f64) -> f64 {
+        use FaderCurve::*;
+
+        let vol = vol.clamp(0.0, 1.0);
+        match self {
+            Linear => vol,
+            AudioTaper => vol.powf(AUDIO_TAPER_EXP.recip()),
+            Db => {
+                if vol < f64::EPSILON {
+                    return 0.0;
+                }
+
+                let db = 20.0 * vol.log10();
+                (1.0 - db / DB_FLOOR).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
@@ -0,0 +1,429 @@
+use std::{collections::BTreeMap, fmt, path::PathBuf};
+
+use crate::midi;
+
+mod tag {
+    use crate::midi::Tag;
+
+    pub const NOTE_OFF: Tag = Tag::from(0x80);
+    pub const NOTE_ON: Tag = Tag::from(0x90);
+    pub const CC: Tag = Tag::from(0xb0);
+    pub const PITCH_BEND: Tag = Tag::from(0xe0);
+}
+
+const APP_DIR: &str = "media-player-controller";
+const FILE_NAME: &str = "midi_mapping";
+
+/// Action triggered by a mapped MIDI message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    PlayPause,
+    Stop,
+    Previous,
+    Next,
+    StepBackward,
+    StepForward,
+    Mute,
+    Volume,
+    Balance,
+    Shuffle,
+    Loop,
+    Marker,
+}
+
+impl Action {
+    pub const ALL: [Action; 12] = [
+        Action::PlayPause,
+        Action::Stop,
+        Action::Previous,
+        Action::Next,
+        Action::StepBackward,
+        Action::StepForward,
+        Action::Mute,
+        Action::Volume,
+        Action::Balance,
+        Action::Shuffle,
+        Action::Loop,
+        Action::Marker,
+    ];
+
+    pub fn name(self) -> &'static str {
+        use Action::*;
+        match self {
+            PlayPause => "play_pause",
+            Stop => "stop",
+            Previous => "previous",
+            Next => "next",
+            StepBackward => "step_backward",
+            StepForward => "step_forward",
+            Mute => "mute",
+            Volume => "volume",
+            Balance => "balance",
+            Shuffle => "shuffle",
+            Loop => "loop",
+            Marker => "marker",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// MIDI message bound to an [`Action`].
+///
+/// The same message is used to send the feedback for the action
+/// to the device, e.g. to light a LED or to move a motorized fader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    Note { chan: midi::Channel, note: u8 },
+    Cc { chan: midi::Channel, cc: u8 },
+    PitchBend { chan: midi::Channel },
+}
+
+impl Binding {
+    /// Returns the binding & the normalized value for `msg`.
+    ///
+    /// Note on messages are 1.0 unless their velocity is 0,
+    /// note off messages are 0.0.
+    pub fn from_msg(msg: &midi::Msg) -> Option<(Self, f64)> {
+        let buf = msg.inner();
+        let &tag_chan = buf.first()?;
+        let chan = midi::Channel::from(tag_chan);
+
+        match midi::Tag::from_tag_chan(tag_chan) {
+            tag::NOTE_ON => match buf.get(1..=2)? {
+                &[note, velocity] => {
+                    let value = if velocity > 0 { 1.0 } else { 0.0 };
+                    Some((Binding::Note { chan, note }, value))
+                }
+                _ => None,
+            },
+            tag::NOTE_OFF => Some((
+                Binding::Note {
+                    chan,
+                    note: *buf.get(1)?,
+                },
+                0.0,
+            )),
+            tag::CC => match buf.get(1..=2)? {
+                &[cc, value] => Some((Binding::Cc { chan, cc }, value as f64 / 127.0)),
+                _ => None,
+            },
+            tag::PITCH_BEND => {
+                let value = midi::normalized_f64::from_be(buf.get(1..=2)?).ok()?;
+                Some((Binding::PitchBend { chan }, value))
+            }
+            _ => None,
+        }
+    }
+
+    /// Builds the message reflecting the normalized `value`.
+    pub fn feedback(self, value: f64) -> midi::Msg {
+        let value = value.clamp(0.0, 1.0);
+        match self {
+            Binding::Note { chan, note } => {
+                let velocity = if value >= 0.5 { 127 } else { 0 };
+                [tag::NOTE_ON | chan, note, velocity].into()
+            }
+            Binding::Cc { chan, cc } => [tag::CC | chan, cc, (value * 127.0).round() as u8].into(),
+            Binding::PitchBend { chan } => {
+                let two_bytes = midi::normalized_f64::to_be(value).unwrap();
+                [tag::PITCH_BEND | chan, two_bytes[0], two_bytes[1]].into()
+            }
+        }
+    }
+}
+
+/// Channels are displayed & parsed in the range `1..=16`.
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Binding::Note { chan, note } => write!(f, "note {} {note}", u8::from(chan) + 1),
+            Binding::Cc { chan, cc } => write!(f, "cc {} {cc}", u8::from(chan) + 1),
+            Binding::PitchBend { chan } => write!(f, "pitch_bend {}", u8::from(chan) + 1),
+        }
+    }
+}
+
+impl std::str::FromStr for Binding {
+    type Err = ();
+
+    fn from_str(binding: &str) -> Result<Self, ()> {
+        let mut fields = binding.split_whitespace();
+        let kind = fields.next().ok_or(())?;
+
+        let chan = fields.next().ok_or(())?.parse::<u8>().map_err(drop)?;
+        if !(1..=16).contains(&chan) {
+            return Err(());
+        }
+        let chan = midi::Channel::from(chan - 1);
+
+        let mut number = || -> Result<u8, ()> {
+            let number = fields.next().ok_or(())?.parse::<u8>().map_err(drop)?;
+            (number < 0x80).then_some(number).ok_or(())
+        };
+
+        let binding = match kind {
+            "note" => Binding::Note {
+                chan,
+                note: number()?,
+            },
+            "cc" => Binding::Cc {
+                chan,
+                cc: number()?,
+            },
+            "pitch_bend" => Binding::PitchBend { chan },
+            _ => return Err(()),
+        };
+
+        if fields.next().is_some() {
+            return Err(());
+        }
+
+        Ok(binding)
+    }
+}
+
+/// User defined MIDI mapping.
+///
+/// The mapping file contains one action per line, followed by the
+/// MIDI message it is bound to. Empty lines & lines starting with `#`
+/// are ignored. Ex.:
+///
+/// ```text
+/// play_pause note 1 60
+/// volume     cc 1 7
+/// balance    pitch_bend 2
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Mapping(BTreeMap<Action, Binding>);
+
+impl Mapping {
+    /// Path to the mapping file in the user config directory.
+    pub fn path() -> PathBuf {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .unwrap_or_default();
+
+        config_dir.join(APP_DIR).join(FILE_NAME)
+    }
+
+    /// Loads the mapping file, starting with an empty mapping if the file doesn't exist.
+    pub fn load() -> Result<Self, super::Error> {
+        match std::fs::read_to_string(Self::path()) {
+            Ok(mapping) => Ok(Self::parse(&mapping)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Saves the mapping, keeping the comments & the lines which couldn't be parsed.
+    pub fn save(&self) -> Result<(), super::Error> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let prev_mapping = match std::fs::read_to_string(&path) {
+            Ok(prev_mapping) => prev_mapping,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+        std::fs::write(path, self.update(&prev_mapping))?;
+
+        Ok(())
+    }
+
+    fn parse(mapping_str: &str) -> Self {
+        let mut mapping = Self::default();
+        for line in mapping_str.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match Self::parse_entry(line) {
+                Some((action, binding)) => mapping.set(action, binding),
+                None => log::warn!("Ignoring MIDI mapping {line}"),
+            }
+        }
+
+        mapping
+    }
+
+    fn parse_entry(line: &str) -> Option<(Action, Binding)> {
+        let (action, binding) = line.trim().split_once(char::is_whitespace)?;
+        Some((Action::from_name(action)?, binding.parse::<Binding>().ok()?))
+    }
+
+    /// Rewrites the entries of `mapping_str` after this mapping.
+    ///
+    /// Entries are updated in place & new entries are appended.
+    /// Other lines are kept as is.
+    fn update(&self, mapping_str: &str) -> String {
+        let mut pending = self.0.clone();
+
+        let mut updated = String::new();
+        for line in mapping_str.lines() {
+            match Self::parse_entry(line) {
+                Some((action, prev_binding)) => match pending.remove(&action) {
+                    Some(binding) if binding == prev_binding => {
+                        updated.push_str(line);
+                        updated.push('\n');
+                    }
+                    Some(binding) => updated.push_str(&format!("{} {binding}\n", action.name())),
+                    // Entries for unbound actions & duplicates are dropped.
+                    None => (),
+                },
+                None => {
+                    updated.push_str(line);
+                    updated.push('\n');
+                }
+            }
+        }
+
+        for (action, binding) in pending {
+            updated.push_str(&format!("{} {binding}\n", action.name()));
+        }
+
+        updated
+    }
+
+    pub fn get(&self, action: Action) -> Option<Binding> {
+        self.0.get(&action).copied()
+    }
+
+    /// Returns the action bound to `binding`.
+    pub fn action(&self, binding: Binding) -> Option<Action> {
+        self.0
+            .iter()
+            .find_map(|(&action, &cur)| (cur == binding).then_some(action))
+    }
+
+    /// Binds `action` to `binding`, unbinding any action previously bound to it.
+    pub fn set(&mut self, action: Action, binding: Binding) {
+        self.0.retain(|_, cur| *cur != binding);
+        self.0.insert(action, binding);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binding_from_str() {
+        let chan = midi::Channel::from(0);
+        assert_eq!("note 1 60".parse(), Ok(Binding::Note { chan, note: 60 }));
+        assert_eq!(" cc  1 7 ".parse(), Ok(Binding::Cc { chan, cc: 7 }));
+
+        let chan = midi::Channel::from(15);
+        assert_eq!("pitch_bend 16".parse(), Ok(Binding::PitchBend { chan }));
+
+        for invalid in [
+            "",
+            "note",
+            "note 1",
+            "note 0 60",
+            "note 17 60",
+            "cc 1 128",
+            "cc 1 7 8",
+            "pitch_bend 1 2",
+            "sysex 1 2",
+        ] {
+            assert_eq!(invalid.parse::<Binding>(), Err(()), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn binding_display_round_trip() {
+        for binding in [
+            Binding::Note {
+                chan: midi::Channel::from(3),
+                note: 0,
+            },
+            Binding::Cc {
+                chan: midi::Channel::from(0),
+                cc: 127,
+            },
+            Binding::PitchBend {
+                chan: midi::Channel::from(15),
+            },
+        ] {
+            assert_eq!(binding.to_string().parse(), Ok(binding));
+        }
+    }
+
+    #[test]
+    fn binding_from_msg_feedback() {
+        let binding = Binding::Cc {
+            chan: midi::Channel::from(2),
+            cc: 7,
+        };
+        let msg = binding.feedback(1.0);
+        assert_eq!(msg.inner(), [0xb2, 7, 127]);
+        assert_eq!(Binding::from_msg(&msg), Some((binding, 1.0)));
+    }
+
+    const MAPPING: &str = "\
+# Transport
+play_pause note 1 60
+unknown note 1 61
+volume     cc 1 7
+
+balance pitch_bend 2
+";
+
+    #[test]
+    fn parse() {
+        let mapping = Mapping::parse(MAPPING);
+
+        let chan = midi::Channel::from(0);
+        assert_eq!(
+            mapping.get(Action::PlayPause),
+            Some(Binding::Note { chan, note: 60 }),
+        );
+        assert_eq!(
+            mapping.get(Action::Volume),
+            Some(Binding::Cc { chan, cc: 7 })
+        );
+        assert_eq!(
+            mapping.get(Action::Balance),
+            Some(Binding::PitchBend {
+                chan: midi::Channel::from(1),
+            }),
+        );
+        assert_eq!(mapping.get(Action::Stop), None);
+    }
+
+    #[test]
+    fn update_keeps_other_lines() {
+        assert_eq!(Mapping::parse(MAPPING).update(MAPPING), MAPPING);
+    }
+
+    #[test]
+    fn update_rewrites_entries() {
+        let chan = midi::Channel::from(0);
+        let mut mapping = Mapping::parse(MAPPING);
+        // Takes the binding over from play_pause.
+        mapping.set(Action::Stop, Binding::Note { chan, note: 60 });
+        mapping.set(Action::Volume, Binding::Cc { chan, cc: 8 });
+
+        let updated = mapping.update(MAPPING);
+        assert_eq!(
+            updated,
+            "\
+# Transport
+unknown note 1 61
+volume cc 1 8
+
+balance pitch_bend 2
+stop note 1 60
+",
+        );
+
+        let reloaded = Mapping::parse(&updated);
+        assert_eq!(reloaded.0, mapping.0);
+    }
+}
@@ -1,7 +1,8 @@
 pub mod data;
-pub use data::{PlaybackStatus, Timecode, Track};
+pub use data::{LoopStatus, Markers, PlaybackStatus, Timecode, Track};
 
 mod device;
+pub use device::{GENERIC_MIDI, MMC, MTC, OSC};
 
 pub mod error;
 pub use error::Error;
@@ -9,6 +10,12 @@ pub use error::Error;
 pub mod event;
 pub use event::{AppEvent, CtrlSurfEvent};
 
+pub mod fader_curve;
+pub use fader_curve::FaderCurve;
+
+pub mod mapping;
+pub use mapping::Mapping;
+
 mod factory;
 use factory::Buildable;
 pub use factory::{ControlSurfaceArc, FACTORY};
@@ -35,4 +42,72 @@ pub trait ControlSurface: Send + 'static {
 
     #[must_use]
     fn reset(&mut self) -> Vec<Msg>;
+
+    /// Resets the device and takes it offline.
+    ///
+    /// After this, the connection must be started again.
+    #[must_use]
+    fn disconnect(&mut self) -> Vec<Msg>;
+
+    /// Checks that the device is still connected.
+    ///
+    /// Called periodically by the app once connected. Implementations send
+    /// a message which the device answers and report [`Msg::connection_lost`]
+    /// if the device didn't answer since the previous call.
+    #[must_use]
+    fn keepalive(&mut self) -> Vec<Msg> {
+        Msg::none()
+    }
+
+    /// Sets the response curve of the volume faders.
+    #[must_use]
+    fn set_fader_curve(&mut self, _curve: FaderCurve) -> Vec<Msg> {
+        Msg::none()
+    }
+
+    /// Sets the frame rate of the generated timecodes.
+    #[must_use]
+    fn set_frame_rate(&mut self, _rate: crate::midi::smpte::FrameRate) -> Vec<Msg> {
+        Msg::none()
+    }
+
+    /// Number of MIDI port pairs used by this Control Surface.
+    fn port_pairs(&self) -> usize {
+        1
+    }
+
+    /// Handles a message received on the port pair at index `port`.
+    #[must_use]
+    fn msg_from_port(&mut self, port: usize, msg: crate::midi::Msg) -> Vec<Msg> {
+        if port == 0 {
+            self.msg_from_device(msg)
+        } else {
+            Msg::none()
+        }
+    }
+
+    /// Whether the device answers a handshake during the connection.
+    ///
+    /// Devices without a handshake can't be found by scanning the ports,
+    /// the connection is started as soon as the output port is connected.
+    fn has_handshake(&self) -> bool {
+        true
+    }
+
+    /// Whether this Control Surface uses OSC over UDP instead of the MIDI ports.
+    fn uses_osc(&self) -> bool {
+        false
+    }
+
+    /// Handles an OSC message received from `client`.
+    #[must_use]
+    fn msg_from_osc(&mut self, _client: std::net::SocketAddr, _msg: crate::osc::Msg) -> Vec<Msg> {
+        Msg::none()
+    }
+
+    /// Handles a tick previously requested using [`Msg::ScheduleTick`].
+    #[must_use]
+    fn tick(&mut self) -> Vec<Msg> {
+        Msg::none()
+    }
 }
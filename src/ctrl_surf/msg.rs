@@ -1,11 +1,23 @@
+use std::{net::SocketAddr, time::Duration};
+
 use super::CtrlSurfEvent;
-use crate::midi;
+use crate::{midi, osc};
 
 #[derive(Debug)]
 pub enum Msg {
     ToApp(CtrlSurfEvent),
     ToDevice(midi::Msg),
+    /// Message for the device on the port pair at the given index.
+    ///
+    /// Index 0 is the main port pair, as used by `ToDevice`.
+    ToDeviceAt(usize, midi::Msg),
+    /// Message for the OSC client at the given address.
+    ToOsc(SocketAddr, osc::Msg),
     ConnectionStatus(ConnectionStatus),
+    /// Requests a call to `tick` after the specified delay.
+    ///
+    /// A pending tick is replaced by the new request.
+    ScheduleTick(Duration),
 }
 
 impl Msg {
@@ -20,6 +32,14 @@ impl Msg {
     pub fn from_connection_result(res: Result<(), super::Error>) -> Self {
         Self::ConnectionStatus(ConnectionStatus::Result(res))
     }
+
+    pub fn connection_lost() -> Self {
+        Self::ConnectionStatus(ConnectionStatus::Lost)
+    }
+
+    pub fn schedule_tick(delay: Duration) -> Self {
+        Self::ScheduleTick(delay)
+    }
 }
 
 impl super::event::CtrlSurfEvent {
@@ -68,6 +88,10 @@ impl<const S: usize> From<[u8; S]> for Msg {
 pub enum ConnectionStatus {
     InProgress,
     Result(Result<(), super::Error>),
+    /// The device stopped answering the keepalive messages.
+    ///
+    /// The Control Surface is disconnected and the connection must be started again.
+    Lost,
 }
 
 impl ConnectionStatus {
@@ -0,0 +1,206 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    ctrl_surf::{msg::ConnectionStatus, AppEvent, ControlSurface, CtrlSurfEvent, FaderCurve, Msg},
+    midi,
+};
+
+struct Member {
+    surface: Box<dyn ControlSurface>,
+    next_tick: Option<Instant>,
+}
+
+/// A Control Surface made of several devices.
+///
+/// Each member device uses its own MIDI port pair, in the order
+/// in which the members were added. The first member is the main unit.
+///
+/// App events are dispatched to all the members, so that the devices
+/// share the same player, and the connection succeeds once all the
+/// members are connected. The data requests from the members are
+/// coalesced and only sent once all the members are connected.
+#[derive(Default)]
+pub struct Composite {
+    members: Vec<Member>,
+    is_data_requested: bool,
+}
+
+impl Composite {
+    pub fn with(mut self, surface: impl ControlSurface) -> Self {
+        self.members.push(Member {
+            surface: Box::new(surface),
+            next_tick: None,
+        });
+
+        self
+    }
+
+    fn is_fully_connected(&self) -> bool {
+        self.members
+            .iter()
+            .all(|member| member.surface.is_connected())
+    }
+
+    /// Adapts the messages from the member at `port` to the Composite.
+    ///
+    /// Data requests are held until [`Self::flush_data_request`].
+    fn wrap(&mut self, port: usize, msg_list: Vec<Msg>) -> Vec<Msg> {
+        use ConnectionStatus::*;
+
+        let mut list = Vec::with_capacity(msg_list.len());
+        let mut must_schedule = false;
+
+        for msg in msg_list {
+            match msg {
+                Msg::ToApp(CtrlSurfEvent::DataRequest) => self.is_data_requested = true,
+                Msg::ToDevice(msg) => list.push(Msg::ToDeviceAt(port, msg)),
+                Msg::ToDeviceAt(_, _) => {
+                    log::warn!("Nested composite Control Surfaces are not supported");
+                }
+                Msg::ConnectionStatus(InProgress) => (),
+                Msg::ConnectionStatus(Result(Ok(()))) => {
+                    if self.is_fully_connected() {
+                        list.push(Msg::from_connection_result(Ok(())));
+                    }
+                }
+                Msg::ConnectionStatus(Result(Err(err))) => {
+                    list.push(Msg::from_connection_result(Err(err)));
+                }
+                Msg::ConnectionStatus(Lost) => list.push(Msg::connection_lost()),
+                Msg::ScheduleTick(delay) => {
+                    self.members[port].next_tick = Some(Instant::now() + delay);
+                    must_schedule = true;
+                }
+                msg @ (Msg::ToApp(_) | Msg::ToOsc(..)) => list.push(msg),
+            }
+        }
+
+        if must_schedule {
+            list.extend(self.schedule_tick());
+        }
+
+        list
+    }
+
+    /// Adds the pending data request to `list` if all the members are connected.
+    fn flush_data_request(&mut self, mut list: Vec<Msg>) -> Vec<Msg> {
+        if self.is_data_requested && self.is_fully_connected() {
+            self.is_data_requested = false;
+            list.push(CtrlSurfEvent::DataRequest.to_app());
+        }
+
+        list
+    }
+
+    /// Schedules a tick for the earliest member tick.
+    fn schedule_tick(&self) -> Option<Msg> {
+        let next_tick = self
+            .members
+            .iter()
+            .filter_map(|member| member.next_tick)
+            .min()?;
+
+        let delay = next_tick.saturating_duration_since(Instant::now());
+        Some(Msg::schedule_tick(delay.max(Duration::from_millis(1))))
+    }
+
+    /// Calls `f` on each member and gathers the resulting messages.
+    fn for_each(&mut self, mut f: impl FnMut(&mut dyn ControlSurface) -> Vec<Msg>) -> Vec<Msg> {
+        let mut list = Vec::new();
+        for port in 0..self.members.len() {
+            let msg_list = f(self.members[port].surface.as_mut());
+            list.extend(self.wrap(port, msg_list));
+        }
+
+        self.flush_data_request(list)
+    }
+}
+
+impl ControlSurface for Composite {
+    fn start_connection(&mut self) -> Vec<Msg> {
+        let mut list = self.for_each(|surface| surface.start_connection());
+        list.push(Msg::connetion_in_progress());
+
+        list
+    }
+
+    fn abort_connection(&mut self) -> Vec<Msg> {
+        self.for_each(|surface| surface.abort_connection())
+    }
+
+    fn msg_from_device(&mut self, msg: midi::Msg) -> Vec<Msg> {
+        self.msg_from_port(0, msg)
+    }
+
+    fn msg_from_port(&mut self, port: usize, msg: midi::Msg) -> Vec<Msg> {
+        let msg_list = match self.members.get_mut(port) {
+            Some(member) => member.surface.msg_from_device(msg),
+            None => {
+                log::warn!("Message from unknown port pair {port}");
+                return Msg::none();
+            }
+        };
+
+        let list = self.wrap(port, msg_list);
+        self.flush_data_request(list)
+    }
+
+    fn event_from_app(&mut self, event: AppEvent) -> Vec<Msg> {
+        self.for_each(|surface| surface.event_from_app(event.clone()))
+    }
+
+    fn is_connected(&self) -> bool {
+        self.is_fully_connected()
+    }
+
+    fn reset(&mut self) -> Vec<Msg> {
+        self.for_each(|surface| surface.reset())
+    }
+
+    fn disconnect(&mut self) -> Vec<Msg> {
+        self.is_data_requested = false;
+        self.for_each(|surface| surface.disconnect())
+    }
+
+    fn keepalive(&mut self) -> Vec<Msg> {
+        self.for_each(|surface| surface.keepalive())
+    }
+
+    fn set_fader_curve(&mut self, curve: FaderCurve) -> Vec<Msg> {
+        self.for_each(|surface| surface.set_fader_curve(curve))
+    }
+
+    fn port_pairs(&self) -> usize {
+        self.members.len()
+    }
+
+    fn has_handshake(&self) -> bool {
+        self.members
+            .iter()
+            .any(|member| member.surface.has_handshake())
+    }
+
+    fn tick(&mut self) -> Vec<Msg> {
+        let now = Instant::now();
+
+        let mut list = Vec::new();
+        for port in 0..self.members.len() {
+            let member = &mut self.members[port];
+            match member.next_tick {
+                Some(next_tick) if next_tick <= now => (),
+                _ => continue,
+            }
+
+            member.next_tick = None;
+            let msg_list = member.surface.tick();
+            list.extend(self.wrap(port, msg_list));
+        }
+
+        // Reschedule for members which are still waiting.
+        if !list.iter().any(|msg| matches!(msg, Msg::ScheduleTick(_))) {
+            list.extend(self.schedule_tick());
+        }
+
+        self.flush_data_request(list)
+    }
+}
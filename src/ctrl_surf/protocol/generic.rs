@@ -0,0 +1,271 @@
+use crate::{
+    ctrl_surf::{
+        event::{self, *},
+        mapping::{Action, Binding, Mapping},
+        FaderCurve, LoopStatus, Msg,
+    },
+    midi,
+};
+
+/// Threshold above which a normalized value is considered as pressed.
+const PRESSED_THRSD: f64 = 0.5;
+
+/// Control Surface driven by a user defined [`Mapping`].
+///
+/// Plain MIDI devices don't perform any handshake,
+/// so the device is considered connected right away.
+#[derive(Debug)]
+pub struct Generic {
+    mapping: Mapping,
+    is_connected: bool,
+    is_playing: bool,
+    is_muted: bool,
+    shuffle: bool,
+    loop_status: LoopStatus,
+    volume: Option<f64>,
+    balance: f64,
+    fader_curve: FaderCurve,
+}
+
+impl Generic {
+    pub fn new(mapping: Mapping) -> Self {
+        Self {
+            mapping,
+            is_connected: false,
+            is_playing: false,
+            is_muted: false,
+            shuffle: false,
+            loop_status: LoopStatus::None,
+            volume: None,
+            balance: 0.0,
+            fader_curve: FaderCurve::default(),
+        }
+    }
+}
+
+impl crate::ctrl_surf::ControlSurface for Generic {
+    fn start_connection(&mut self) -> Vec<Msg> {
+        log::debug!("Connecting to generic MIDI device");
+
+        self.is_connected = true;
+
+        vec![
+            Msg::from_connection_result(Ok(())),
+            CtrlSurfEvent::DataRequest.to_app(),
+        ]
+    }
+
+    fn abort_connection(&mut self) -> Vec<Msg> {
+        Msg::none()
+    }
+
+    fn msg_from_device(&mut self, msg: midi::Msg) -> Vec<Msg> {
+        use Action::*;
+
+        let (binding, value) = match Binding::from_msg(&msg) {
+            Some(binding_value) => binding_value,
+            None => return Msg::none(),
+        };
+        let action = match self.mapping.action(binding) {
+            Some(action) => action,
+            None => {
+                log::debug!("Unmapped MIDI message {}", msg.display());
+                return Msg::none();
+            }
+        };
+
+        match action {
+            Volume => {
+                let vol = self.fader_curve.to_volume(value);
+                self.volume = Some(vol);
+                return Mixer::Volume(vol).to_app().into();
+            }
+            Balance => {
+                self.balance = 2.0 * value - 1.0;
+                return Mixer::Balance(self.balance).to_app().into();
+            }
+            _ if value < PRESSED_THRSD => (),
+            PlayPause => return Transport::PlayPause.to_app().into(),
+            Stop => return Transport::Stop.to_app().into(),
+            Previous => return Transport::Previous.to_app().into(),
+            Next => return Transport::Next.to_app().into(),
+            StepBackward => return Transport::StepBackward(1).to_app().into(),
+            StepForward => return Transport::StepForward(1).to_app().into(),
+            Mute => {
+                if self.is_muted {
+                    return Mixer::Unmute.to_app().into();
+                } else {
+                    return Mixer::Mute.to_app().into();
+                }
+            }
+            Shuffle => return Transport::SetShuffle(!self.shuffle).to_app().into(),
+            Loop => {
+                return Transport::SetLoopStatus(self.loop_status.next())
+                    .to_app()
+                    .into()
+            }
+            Marker => return CtrlSurfEvent::Marker.to_app().into(),
+        }
+
+        Msg::none()
+    }
+
+    fn event_from_app(&mut self, event: AppEvent) -> Vec<Msg> {
+        if !self.is_connected {
+            log::debug!("Ignoring App event: Control surface not connected.");
+            return Msg::none();
+        }
+
+        use AppEvent::*;
+        match event {
+            Transport(event) => {
+                use event::Transport::*;
+                match event {
+                    Play => return self.app_playing(true),
+                    Pause => return self.app_playing(false),
+                    Stop => return self.reset(),
+                    _ => (),
+                }
+            }
+            Mixer(mixer) => {
+                use event::Mixer::*;
+                match mixer {
+                    Volume(vol) => return self.app_volume(vol),
+                    Mute => return self.app_muted(true),
+                    Unmute => return self.app_muted(false),
+                    Balance(balance) => return self.app_balance(balance),
+                }
+            }
+            NewApp(app) => {
+                log::debug!("New application {app}");
+                return CtrlSurfEvent::DataRequest.to_app().into();
+            }
+            Data(data) => {
+                use event::Data::*;
+                match data {
+                    PlaybackStatus(status) => return self.app_playing(status.is_playing()),
+                    Shuffle(shuffle) => {
+                        self.shuffle = shuffle;
+                        return self.led_feedback(Action::Shuffle, shuffle);
+                    }
+                    LoopStatus(status) => {
+                        self.loop_status = status;
+                        let is_looping = status != crate::ctrl_surf::LoopStatus::None;
+                        return self.led_feedback(Action::Loop, is_looping);
+                    }
+                    Markers(markers) => {
+                        return self.led_feedback(Action::Marker, !markers.is_empty());
+                    }
+                    _ => (),
+                }
+            }
+            Strip(..) => (),
+        }
+
+        Msg::none()
+    }
+
+    fn set_fader_curve(&mut self, curve: FaderCurve) -> Vec<Msg> {
+        self.fader_curve = curve;
+
+        match self.volume {
+            Some(vol) if self.is_connected => self.feedback(Action::Volume, curve.to_position(vol)),
+            _ => Msg::none(),
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.is_connected
+    }
+
+    fn has_handshake(&self) -> bool {
+        false
+    }
+
+    /// Resets the transport feedback.
+    ///
+    /// The mixer feedback is kept since the volume & balance
+    /// are sent again along with the next player's data.
+    fn reset(&mut self) -> Vec<Msg> {
+        self.is_playing = false;
+        self.is_muted = false;
+        self.shuffle = false;
+        self.loop_status = LoopStatus::None;
+
+        let mut list = Vec::new();
+        for action in [
+            Action::PlayPause,
+            Action::Mute,
+            Action::Shuffle,
+            Action::Loop,
+            Action::Marker,
+        ] {
+            list.extend(self.led_feedback(action, false));
+        }
+
+        list
+    }
+
+    fn disconnect(&mut self) -> Vec<Msg> {
+        if !self.is_connected {
+            return Msg::none();
+        }
+
+        log::debug!("Disconnecting from generic MIDI device");
+
+        let mut list = self.reset();
+        self.volume = None;
+        self.balance = 0.0;
+        list.extend(self.feedback(Action::Volume, 0.0));
+        list.extend(self.feedback(Action::Balance, 0.5));
+
+        self.is_connected = false;
+
+        list
+    }
+}
+
+/// App events.
+impl Generic {
+    fn app_playing(&mut self, is_playing: bool) -> Vec<Msg> {
+        self.is_playing = is_playing;
+        self.led_feedback(Action::PlayPause, is_playing)
+    }
+
+    fn app_muted(&mut self, is_muted: bool) -> Vec<Msg> {
+        self.is_muted = is_muted;
+        self.led_feedback(Action::Mute, is_muted)
+    }
+
+    fn app_volume(&mut self, vol: f64) -> Vec<Msg> {
+        // Don't send back the volume the device just set,
+        // it would fight with the user moving the fader.
+        if matches!(self.volume, Some(cur) if (cur - vol).abs() < f64::EPSILON) {
+            return Msg::none();
+        }
+
+        self.volume = Some(vol);
+        self.feedback(Action::Volume, self.fader_curve.to_position(vol))
+    }
+
+    fn app_balance(&mut self, balance: f64) -> Vec<Msg> {
+        if (self.balance - balance).abs() < f64::EPSILON {
+            return Msg::none();
+        }
+
+        self.balance = balance;
+        self.feedback(Action::Balance, (balance + 1.0) / 2.0)
+    }
+
+    /// Builds the feedback for `action` if it is mapped.
+    fn feedback(&self, action: Action, value: f64) -> Vec<Msg> {
+        match self.mapping.get(action) {
+            Some(binding) => binding.feedback(value).to_device().into(),
+            None => Msg::none(),
+        }
+    }
+
+    fn led_feedback(&self, action: Action, is_on: bool) -> Vec<Msg> {
+        self.feedback(action, if is_on { 1.0 } else { 0.0 })
+    }
+}
@@ -0,0 +1,479 @@
+use once_cell::sync::Lazy;
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    ctrl_surf::{
+        self,
+        event::{self, *},
+        FaderCurve, Msg,
+    },
+    midi,
+};
+
+mod connection {
+    pub const HUI_ID: [u8; 5] = [0x00, 0x00, 0x66, 0x05, 0x00];
+
+    pub const PING: [u8; 3] = [0x90, 0x00, 0x00];
+    pub const PING_REPLY: [u8; 3] = [0x90, 0x00, 0x7f];
+}
+
+/// Switches & LEDs are addressed using a zone and a port.
+mod switch {
+    use crate::midi::Tag;
+    pub const TAG: Tag = Tag::from(0xb0);
+
+    pub const ZONE_IN: u8 = 0x0f;
+    pub const PORT_IN: u8 = 0x2f;
+    pub const ZONE_OUT: u8 = 0x0c;
+    pub const PORT_OUT: u8 = 0x2c;
+
+    pub const PORT_MASK: u8 = 0x0f;
+    pub const ON: u8 = 0x40;
+    pub const OFF: u8 = 0x00;
+
+    // (zone, port)
+    pub const FADER_TOUCHED: (u8, u8) = (0x00, 0x00);
+    pub const MUTE: (u8, u8) = (0x00, 0x02);
+    pub const REWIND: (u8, u8) = (0x0e, 0x01);
+    pub const FAST_FWD: (u8, u8) = (0x0e, 0x02);
+    pub const STOP: (u8, u8) = (0x0e, 0x03);
+    pub const PLAY: (u8, u8) = (0x0e, 0x04);
+}
+
+mod fader {
+    use crate::midi::Tag;
+    pub const TAG: Tag = Tag::from(0xb0);
+
+    /// Most significant 7 bits for first strip.
+    pub const HI: u8 = 0x00;
+    /// Least significant 7 bits for first strip.
+    pub const LO: u8 = 0x20;
+
+    pub const MAX: u16 = 0x3fff;
+}
+
+mod display {
+    pub const SCRIBBLE_STRIP: u8 = 0x10;
+    pub const TIMECODE: u8 = 0x11;
+
+    pub const SCRIBBLE_LEN: usize = 4;
+    pub const TIMECODE_DIGITS: usize = 8;
+}
+
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+
+static NO_APP: Lazy<Arc<str>> = Lazy::new(|| "_NOAPP_".into());
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum FaderState {
+    Released,
+    Touched { last_volume: Option<f64> },
+}
+
+/// Mackie HUI protocol.
+///
+/// Only the first strip is used for the volume & mute.
+#[derive(Debug)]
+pub struct Hui {
+    state: State,
+    app: Arc<str>,
+    is_muted: bool,
+    fader_state: FaderState,
+    fader_curve: FaderCurve,
+    /// Most significant 7 bits received for the fader position.
+    fader_hi: Option<u8>,
+    /// Whether the device replied to a ping since last keepalive.
+    is_alive: bool,
+    /// Last zone selected by the device.
+    zone: Option<u8>,
+    last_tc: [u8; display::TIMECODE_DIGITS],
+}
+
+impl Default for Hui {
+    fn default() -> Self {
+        Self {
+            state: State::Disconnected,
+            app: NO_APP.clone(),
+            is_muted: false,
+            fader_state: FaderState::Released,
+            fader_curve: FaderCurve::default(),
+            fader_hi: None,
+            is_alive: false,
+            zone: None,
+            last_tc: [0xff; display::TIMECODE_DIGITS],
+        }
+    }
+}
+
+impl crate::ctrl_surf::ControlSurface for Hui {
+    fn start_connection(&mut self) -> Vec<Msg> {
+        log::debug!("Attempt to connect to HUI device");
+
+        self.state = State::Connecting;
+
+        vec![Msg::from(connection::PING), Msg::connetion_in_progress()]
+    }
+
+    fn abort_connection(&mut self) -> Vec<Msg> {
+        if self.state == State::Connecting {
+            log::debug!("Aborting connection to HUI device");
+
+            self.state = State::Disconnected;
+        }
+
+        Msg::none()
+    }
+
+    fn msg_from_device(&mut self, msg: crate::midi::Msg) -> Vec<Msg> {
+        let buf = msg.inner();
+
+        if buf == connection::PING_REPLY {
+            return self.device_ping_reply();
+        }
+
+        match buf {
+            [tag, switch::ZONE_IN, zone] if *tag == switch::TAG => {
+                self.zone = Some(*zone);
+            }
+            [tag, switch::PORT_IN, value] if *tag == switch::TAG => {
+                if let Some(zone) = self.zone {
+                    let port = value & switch::PORT_MASK;
+                    let is_pressed = value & switch::ON == switch::ON;
+                    return self.device_switch(zone, port, is_pressed);
+                }
+            }
+            [tag, fader::HI, hi] if *tag == fader::TAG => {
+                self.fader_hi = Some(*hi);
+            }
+            [tag, fader::LO, lo] if *tag == fader::TAG => {
+                if let Some(hi) = self.fader_hi.take() {
+                    let value = ((hi as u16) << 7) | *lo as u16;
+                    let vol = self.fader_curve.to_volume(value as f64 / fader::MAX as f64);
+                    return self.device_fader_moved(vol);
+                }
+            }
+            _ => (),
+        }
+
+        Msg::none()
+    }
+
+    fn event_from_app(&mut self, event: AppEvent) -> Vec<Msg> {
+        if !self.is_connected() {
+            log::debug!("Ignoring App event: Control surface not connected.");
+            return Msg::none();
+        }
+
+        use AppEvent::*;
+        match event {
+            Transport(event) => {
+                use event::Transport::*;
+                match event {
+                    Play => return self.app_play(true),
+                    Pause => return self.app_play(false),
+                    Stop => return self.reset(),
+                    _ => (),
+                }
+            }
+            Mixer(mixer) => {
+                use event::Mixer::*;
+                match mixer {
+                    Volume(vol) => return self.app_volume(vol),
+                    Mute => return self.app_mute(true),
+                    Unmute => return self.app_mute(false),
+                    Balance(_) => (),
+                }
+            }
+            NewApp(app) => {
+                if app != self.app {
+                    log::debug!("New application {app}");
+
+                    let mut list = self.scribble_strip(&app);
+                    self.app = app;
+                    list.push(CtrlSurfEvent::DataRequest.to_app());
+
+                    return list;
+                }
+            }
+            Data(data) => {
+                use event::Data::*;
+
+                match data {
+                    Position(pos) => return self.app_position(pos),
+                    PlaybackStatus(status) => {
+                        use crate::ctrl_surf::data::PlaybackStatus::*;
+
+                        match status {
+                            Playing => return self.app_play(true),
+                            Paused => return self.app_play(false),
+                            Stopped => return self.reset(),
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            Strip(..) => (),
+        }
+
+        Msg::none()
+    }
+
+    fn keepalive(&mut self) -> Vec<Msg> {
+        if !self.is_connected() {
+            return Msg::none();
+        }
+
+        // The device is pinged on each tick.
+        if !std::mem::replace(&mut self.is_alive, false) {
+            log::warn!("HUI device stopped answering pings");
+            self.state = State::Disconnected;
+
+            return Msg::connection_lost().into();
+        }
+
+        Msg::none()
+    }
+
+    fn set_fader_curve(&mut self, curve: FaderCurve) -> Vec<Msg> {
+        self.fader_curve = curve;
+        Msg::none()
+    }
+
+    fn is_connected(&self) -> bool {
+        self.state == State::Connected
+    }
+
+    fn tick(&mut self) -> Vec<Msg> {
+        if !self.is_connected() {
+            return Msg::none();
+        }
+
+        vec![
+            Msg::from(connection::PING),
+            Msg::schedule_tick(PING_INTERVAL),
+        ]
+    }
+
+    fn reset(&mut self) -> Vec<Msg> {
+        let mut list = Vec::new();
+
+        list.extend(self.led_msg(switch::MUTE, false));
+        list.extend(self.led_msg(switch::STOP, false));
+        list.extend(self.led_msg(switch::PLAY, false));
+
+        self.last_tc = [0xff; display::TIMECODE_DIGITS];
+        list.extend(self.timecode_update(Duration::ZERO));
+        list.extend(self.scribble_strip(""));
+
+        self.is_muted = false;
+        self.app = NO_APP.clone();
+
+        list
+    }
+
+    fn disconnect(&mut self) -> Vec<Msg> {
+        if !self.is_connected() {
+            return Msg::none();
+        }
+
+        log::debug!("Disconnecting from HUI device");
+
+        let mut list = self.reset();
+        list.extend(self.build_fader_msg(0.0));
+
+        // The device goes offline when the pings stop.
+        self.state = State::Disconnected;
+
+        list
+    }
+}
+
+/// Device events.
+impl Hui {
+    fn device_ping_reply(&mut self) -> Vec<Msg> {
+        self.is_alive = true;
+        if self.state != State::Connecting {
+            return Msg::none();
+        }
+
+        log::debug!("Connected to HUI device");
+        self.state = State::Connected;
+        self.last_tc = [0xff; display::TIMECODE_DIGITS];
+
+        vec![
+            Msg::from_connection_result(Ok(())),
+            CtrlSurfEvent::DataRequest.to_app(),
+            Msg::schedule_tick(PING_INTERVAL),
+        ]
+    }
+
+    fn device_switch(&mut self, zone: u8, port: u8, is_pressed: bool) -> Vec<Msg> {
+        use Mixer::*;
+        use Transport::*;
+
+        match ((zone, port), is_pressed) {
+            (switch::FADER_TOUCHED, is_touched) => return self.device_fader_touch(is_touched),
+            (switch::MUTE, true) => {
+                if self.is_muted {
+                    return Unmute.to_app().into();
+                } else {
+                    return Mute.to_app().into();
+                }
+            }
+            (switch::REWIND, true) => return Previous.to_app().into(),
+            (switch::FAST_FWD, true) => return Next.to_app().into(),
+            (switch::STOP, true) => return Stop.to_app().into(),
+            (switch::PLAY, true) => return PlayPause.to_app().into(),
+            _ => (),
+        }
+
+        Msg::none()
+    }
+
+    fn device_fader_touch(&mut self, is_touched: bool) -> Vec<Msg> {
+        use FaderState::*;
+
+        match self.fader_state {
+            Released if is_touched => {
+                self.fader_state = Touched { last_volume: None };
+            }
+            Touched { last_volume } if !is_touched => {
+                self.fader_state = Released;
+                if let Some(vol) = last_volume {
+                    let mut list = vec![Mixer::Volume(vol).to_app()];
+                    list.extend(self.build_fader_msg(vol));
+
+                    return list;
+                }
+            }
+            _ => (),
+        }
+
+        Msg::none()
+    }
+
+    fn device_fader_moved(&mut self, vol: f64) -> Vec<Msg> {
+        match &mut self.fader_state {
+            FaderState::Touched { last_volume } => {
+                *last_volume = Some(vol);
+                Mixer::Volume(vol).to_app().into()
+            }
+            FaderState::Released => {
+                log::warn!("Fader moved but no touch detected => ignoring");
+
+                Msg::none()
+            }
+        }
+    }
+}
+
+/// App events.
+impl Hui {
+    fn app_play(&mut self, is_playing: bool) -> Vec<Msg> {
+        let mut list = self.led_msg(switch::PLAY, is_playing);
+        list.extend(self.led_msg(switch::STOP, false));
+
+        list
+    }
+
+    fn app_mute(&mut self, is_muted: bool) -> Vec<Msg> {
+        self.is_muted = is_muted;
+        self.led_msg(switch::MUTE, is_muted)
+    }
+
+    fn app_volume(&mut self, vol: f64) -> Vec<Msg> {
+        match &mut self.fader_state {
+            FaderState::Released => self.build_fader_msg(vol),
+            FaderState::Touched { last_volume } => {
+                // user touches fader => don't move it before it's released.
+                *last_volume = Some(vol);
+                Msg::none()
+            }
+        }
+    }
+
+    fn app_position(&mut self, pos: Duration) -> Vec<Msg> {
+        self.timecode_update(pos)
+    }
+}
+
+/// Messages to device.
+impl Hui {
+    fn build_fader_msg(&self, vol: f64) -> Vec<Msg> {
+        let pos = self.fader_curve.to_position(vol);
+        let value = (pos * fader::MAX as f64).round() as u16;
+        let tag = u8::from(fader::TAG);
+
+        vec![
+            Msg::from([tag, fader::HI, (value >> 7) as u8]),
+            Msg::from([tag, fader::LO, value as u8 & 0x7f]),
+        ]
+    }
+
+    fn led_msg(&self, (zone, port): (u8, u8), is_on: bool) -> Vec<Msg> {
+        let tag = u8::from(switch::TAG);
+        let state = if is_on { switch::ON } else { switch::OFF };
+
+        vec![
+            Msg::from([tag, switch::ZONE_OUT, zone]),
+            Msg::from([tag, switch::PORT_OUT, state | port]),
+        ]
+    }
+
+    /// Displays the first characters of `text` on the first scribble strip.
+    fn scribble_strip(&self, text: &str) -> Vec<Msg> {
+        let mut payload = [b' '; connection::HUI_ID.len() + 2 + display::SCRIBBLE_LEN];
+        payload[..connection::HUI_ID.len()].copy_from_slice(&connection::HUI_ID);
+        payload[5] = display::SCRIBBLE_STRIP;
+        payload[6] = 0; // strip
+
+        for (byte, c) in payload[7..].iter_mut().zip(text.chars()) {
+            *byte = if c.is_ascii_graphic() { c as u8 } else { b' ' };
+        }
+
+        midi::Msg::new_sysex(&payload).to_device().into()
+    }
+
+    fn timecode_update(&mut self, pos: Duration) -> Vec<Msg> {
+        use std::io::Write;
+
+        let tc = ctrl_surf::Timecode::from(pos);
+
+        let mut digits = [b'0'; display::TIMECODE_DIGITS];
+        let mut cur = std::io::Cursor::new(digits.as_mut_slice());
+        write!(
+            cur,
+            "{:02}{:02}{:02}{:02}",
+            tc.h % 100,
+            tc.m,
+            tc.s,
+            tc.ms / 10
+        )
+        .unwrap();
+
+        // Digits are sent from the right-most.
+        let mut tc = [0u8; display::TIMECODE_DIGITS];
+        for (digit, ascii) in tc.iter_mut().zip(digits.iter().rev()) {
+            *digit = ascii - b'0';
+        }
+
+        if tc == self.last_tc {
+            return Msg::none();
+        }
+        self.last_tc = tc;
+
+        let mut payload = Vec::with_capacity(connection::HUI_ID.len() + 1 + tc.len());
+        payload.extend(connection::HUI_ID);
+        payload.push(display::TIMECODE);
+        payload.extend(tc);
+
+        midi::Msg::new_sysex(&payload).to_device().into()
+    }
+}
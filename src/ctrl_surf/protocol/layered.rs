@@ -0,0 +1,128 @@
+use crate::{
+    ctrl_surf::{msg::ConnectionStatus, AppEvent, ControlSurface, FaderCurve, Msg},
+    midi,
+};
+
+/// Device specific behaviour layered over a generic protocol.
+///
+/// The layer is notified after the protocol handled the corresponding call
+/// and its messages are sent after the protocol's.
+pub trait Layer: Send + 'static {
+    /// Called when the connection to the device is established.
+    #[must_use]
+    fn connected(&mut self) -> Vec<Msg> {
+        Msg::none()
+    }
+
+    /// Called for each app event while connected.
+    #[must_use]
+    fn event_from_app(&mut self, _event: &AppEvent) -> Vec<Msg> {
+        Msg::none()
+    }
+
+    #[must_use]
+    fn reset(&mut self) -> Vec<Msg> {
+        Msg::none()
+    }
+}
+
+/// A Control Surface protocol `P` with the additional [`Layer`] `L`.
+pub struct Layered<P, L> {
+    protocol: P,
+    layer: L,
+}
+
+impl<P: ControlSurface, L: Layer> Layered<P, L> {
+    pub fn new(protocol: P, layer: L) -> Self {
+        Self { protocol, layer }
+    }
+
+    /// Adds the layer's messages if the protocol just got connected.
+    fn with_connection(&mut self, mut msg_list: Vec<Msg>) -> Vec<Msg> {
+        let is_connected = msg_list
+            .iter()
+            .any(|msg| matches!(msg, Msg::ConnectionStatus(ConnectionStatus::Result(Ok(()))),));
+
+        if is_connected {
+            msg_list.extend(self.layer.connected());
+        }
+
+        msg_list
+    }
+}
+
+impl<P: ControlSurface, L: Layer> ControlSurface for Layered<P, L> {
+    fn start_connection(&mut self) -> Vec<Msg> {
+        let msg_list = self.protocol.start_connection();
+        self.with_connection(msg_list)
+    }
+
+    fn abort_connection(&mut self) -> Vec<Msg> {
+        self.protocol.abort_connection()
+    }
+
+    fn msg_from_device(&mut self, msg: midi::Msg) -> Vec<Msg> {
+        let msg_list = self.protocol.msg_from_device(msg);
+        self.with_connection(msg_list)
+    }
+
+    fn event_from_app(&mut self, event: AppEvent) -> Vec<Msg> {
+        if !self.protocol.is_connected() {
+            return self.protocol.event_from_app(event);
+        }
+
+        let layer_msg_list = self.layer.event_from_app(&event);
+        let mut msg_list = self.protocol.event_from_app(event);
+        msg_list.extend(layer_msg_list);
+
+        msg_list
+    }
+
+    fn is_connected(&self) -> bool {
+        self.protocol.is_connected()
+    }
+
+    fn reset(&mut self) -> Vec<Msg> {
+        let mut msg_list = self.protocol.reset();
+        msg_list.extend(self.layer.reset());
+
+        msg_list
+    }
+
+    fn disconnect(&mut self) -> Vec<Msg> {
+        if !self.protocol.is_connected() {
+            return Msg::none();
+        }
+
+        let mut msg_list = self.layer.reset();
+        msg_list.extend(self.protocol.disconnect());
+
+        msg_list
+    }
+
+    fn keepalive(&mut self) -> Vec<Msg> {
+        self.protocol.keepalive()
+    }
+
+    fn set_fader_curve(&mut self, curve: FaderCurve) -> Vec<Msg> {
+        self.protocol.set_fader_curve(curve)
+    }
+
+    fn port_pairs(&self) -> usize {
+        self.protocol.port_pairs()
+    }
+
+    fn has_handshake(&self) -> bool {
+        self.protocol.has_handshake()
+    }
+
+    fn msg_from_port(&mut self, port: usize, msg: midi::Msg) -> Vec<Msg> {
+        let msg_list = self.protocol.msg_from_port(port, msg);
+        self.with_connection(msg_list)
+    }
+
+    fn tick(&mut self) -> Vec<Msg> {
+        let msg_list = self.protocol.tick();
+        self.with_connection(msg_list)
+    }
+}
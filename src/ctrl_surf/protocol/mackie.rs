@@ -1,21 +1,19 @@
 use once_cell::sync::Lazy;
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use crate::{
     ctrl_surf::{
         self,
         event::{self, *},
-        Error, Msg, Timecode,
+        Error, FaderCurve, LoopStatus, Markers, Msg, Timecode,
     },
     midi,
 };
 
 mod connection {
-    pub const MACKIE_ID: [u8; 3] = [0x00, 0x00, 0x66];
-
-    pub const LOGIC_CONTROL_ID: u8 = 0x10;
-    pub const LOGIC_CONTROL_EXT_ID: u8 = 0x11;
-
     pub const QUERY_DEVICE: u8 = 0x00;
     pub const QUERY_HOST: u8 = 0x01;
     pub const HOST_REPLY: u8 = 0x02;
@@ -26,8 +24,7 @@ mod connection {
     //pub const RESET_FADERS: u8 = 0x61;
     //pub const RESET_LEDS: u8 = 0x62;
 
-    // TODO?
-    //pub const GO_OFFLINE: u8 = 0x0f;
+    pub const GO_OFFLINE: u8 = 0x0f;
 }
 
 mod button {
@@ -38,13 +35,34 @@ mod button {
     pub const RELEASED: u8 = 0;
     pub const ON: u8 = PRESSED;
     pub const OFF: u8 = RELEASED;
+    pub const BLINK: u8 = 1;
 
+    pub const REC: u8 = 0;
+    pub const SOLO: u8 = 8;
     pub const MUTE: u8 = 16;
+    pub const MUTE_LAST: u8 = 23;
+    pub const SELECT_FIRST: u8 = 24;
+    pub const SELECT_LAST: u8 = 31;
+    pub const VPOT_PUSH_FIRST: u8 = 32;
+    pub const VPOT_PUSH_LAST: u8 = 39;
+    pub const F1: u8 = 54;
+    pub const F8: u8 = 61;
+    pub const MARKER: u8 = 84;
+    pub const CYCLE: u8 = 86;
+    // Labelled REW / FF on MCU layouts.
     pub const PREVIOUS: u8 = 91;
     pub const NEXT: u8 = 92;
     pub const STOP: u8 = 93;
     pub const PLAY: u8 = 94;
     pub const FADER_TOUCHED: u8 = 104;
+    pub const FADER_TOUCHED_LAST: u8 = 111;
+
+    pub const LAST_LED: u8 = 0x75;
+    pub const LED_COUNT: usize = LAST_LED as usize + 1;
+
+    pub const SMPTE_BEATS: u8 = 53;
+    pub const SMPTE_LED: u8 = 113;
+    pub const BEATS_LED: u8 = 114;
 }
 
 mod display_7_seg {
@@ -52,6 +70,20 @@ mod display_7_seg {
     pub const TAG: Tag = Tag::from(0xb0);
 
     pub const TIME_LEFT_DIGIT: u8 = 0x49;
+    pub const ASSIGNMENT_LEFT_DIGIT: u8 = 0x4b;
+}
+
+mod encoder {
+    use crate::midi::Tag;
+    pub const TAG: Tag = Tag::from(0xb0);
+
+    pub const JOG: u8 = 0x3c;
+    pub const VPOT_FIRST: u8 = 0x10;
+    pub const VPOT_LAST: u8 = 0x17;
+    pub const VPOT_RING_FIRST: u8 = 0x30;
+
+    pub const CCW: u8 = 0x40;
+    pub const TICKS_MASK: u8 = 0x3f;
 }
 
 mod fader {
@@ -61,6 +93,44 @@ mod fader {
     pub const TOUCH_THRSD: u8 = 64;
 }
 
+mod meter {
+    use crate::midi::Tag;
+    pub const TAG: Tag = Tag::from(0xd0);
+
+    pub const MODE: u8 = 0x20;
+    pub const SIGNAL_LED: u8 = 0x01;
+
+    pub const MAX: u8 = 0x0c;
+    pub const FLOOR_DB: f64 = -60.0;
+}
+
+mod lcd {
+    pub const WRITE: u8 = 0x12;
+
+    pub const ROW_LEN: usize = 56;
+    pub const LEN: usize = 2 * ROW_LEN;
+
+    pub const STRIP_LEN: usize = 7;
+}
+
+const JOG_ACCEL_WINDOW: Duration = Duration::from_millis(50);
+const JOG_ACCEL_RAMP: u16 = 4;
+const JOG_MAX_ACCEL: u16 = 8;
+
+const SHUTTLE_HOLD_DELAY: Duration = Duration::from_millis(400);
+const SHUTTLE_INTERVAL: Duration = Duration::from_millis(100);
+const SHUTTLE_ACCEL_PERIOD: Duration = Duration::from_secs(1);
+const SHUTTLE_MAX_STEPS: u16 = 16;
+
+const VPOT_VOLUME_STEP: f64 = 0.01;
+const VPOT_BALANCE_STEP: f64 = 0.05;
+/// Rate is adjusted on a log2 scale.
+const VPOT_RATE_LOG_STEP: f64 = 1f64 / 24f64;
+const RATE_MIN: f64 = 0.25;
+const RATE_MAX: f64 = 4.0;
+
+const STRIPS: usize = 8;
+
 static NO_APP: Lazy<Arc<str>> = Lazy::new(|| "_NOAPP_".into());
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -79,35 +149,313 @@ enum ConnectionStatus {
     ChallengeReplied,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 enum FaderState {
+    #[default]
     Released,
-    Touched { last_volume: Option<f64> },
+    Touched {
+        last_volume: Option<f64>,
+    },
+}
+
+/// Button LED state.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum LedState {
+    #[default]
+    Off,
+    On,
+    Blink,
+}
+
+impl LedState {
+    fn value(self) -> u8 {
+        use LedState::*;
+        match self {
+            Off => button::OFF,
+            On => button::ON,
+            Blink => button::BLINK,
+        }
+    }
+}
+
+impl From<bool> for LedState {
+    fn from(is_on: bool) -> Self {
+        if is_on {
+            LedState::On
+        } else {
+            LedState::Off
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct JogState {
+    last: Option<Instant>,
+    fast_ticks: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum VPotFunc {
+    None,
+    Volume,
+    Balance,
+    Rate,
+}
+
+/// V-Pot LED ring display mode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RingMode {
+    Dot,
+    BoostCut,
+    Wrap,
+    Spread,
+}
+
+impl RingMode {
+    fn next(self) -> Self {
+        use RingMode::*;
+        match self {
+            Dot => BoostCut,
+            BoostCut => Wrap,
+            Wrap => Spread,
+            Spread => Dot,
+        }
+    }
+
+    /// Builds the LED ring value for the normalized `value`.
+    fn ring_value(self, value: f64) -> u8 {
+        use RingMode::*;
+
+        let value = value.clamp(0.0, 1.0);
+        let (mode, pos) = match self {
+            Dot => (0, 1 + (value * 10.0).round() as u8),
+            BoostCut => (1, 1 + (value * 10.0).round() as u8),
+            Wrap => (2, 1 + (value * 10.0).round() as u8),
+            Spread => (3, 1 + (value * 5.0).round() as u8),
+        };
+
+        (mode << 4) | pos
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct VPot {
+    func: VPotFunc,
+    ring: RingMode,
+}
+
+impl VPot {
+    const fn new(func: VPotFunc, ring: RingMode) -> Self {
+        Self { func, ring }
+    }
+}
+
+const DEFAULT_VPOTS: [VPot; 8] = [
+    VPot::new(VPotFunc::Volume, RingMode::Wrap),
+    VPot::new(VPotFunc::Balance, RingMode::Dot),
+    VPot::new(VPotFunc::Rate, RingMode::BoostCut),
+    VPot::new(VPotFunc::None, RingMode::Dot),
+    VPot::new(VPotFunc::None, RingMode::Dot),
+    VPot::new(VPotFunc::None, RingMode::Dot),
+    VPot::new(VPotFunc::None, RingMode::Dot),
+    VPot::new(VPotFunc::None, RingMode::Dot),
+];
+
+/// What the timecode display shows.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum TimeMode {
+    #[default]
+    Elapsed,
+    Remaining,
+    /// Track number in the hours digits followed by the elapsed time.
+    TrackElapsed,
+}
+
+impl TimeMode {
+    fn next(self) -> Self {
+        use TimeMode::*;
+        match self {
+            Elapsed => Remaining,
+            Remaining => TrackElapsed,
+            TrackElapsed => Elapsed,
+        }
+    }
+
+    /// Returns the state of the SMPTE & BEATS LEDs.
+    fn leds(self) -> (bool, bool) {
+        use TimeMode::*;
+        match self {
+            Elapsed => (true, false),
+            Remaining => (false, true),
+            TrackElapsed => (true, true),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ShuttleDirection {
+    Backward,
+    Forward,
+}
+
+/// Transport buttons hold-to-shuttle state.
+///
+/// A short press on PREVIOUS / NEXT selects the previous / next track
+/// when the button is released. Holding the button seeks in the
+/// corresponding direction until it is released.
+#[derive(Clone, Copy, Debug)]
+enum ShuttleState {
+    Idle,
+    Pressed {
+        dir: ShuttleDirection,
+        since: Instant,
+    },
+    Shuttling {
+        dir: ShuttleDirection,
+        since: Instant,
+    },
+}
+
+/// A fader strip bound to a player in multi-strip mode.
+#[derive(Clone, Debug, Default)]
+struct Strip {
+    name: Option<Arc<str>>,
+    is_muted: bool,
+    /// Last volume received from the app.
+    volume: Option<f64>,
+    fader_state: FaderState,
 }
 
 #[derive(Debug)]
 pub struct Mackie {
     device_id: u8,
+    /// Whether the device id is learnt from the device replies.
+    learns_device_id: bool,
+    /// Whether a keepalive query is waiting for a device message.
+    is_keepalive_pending: bool,
+    time_mode: TimeMode,
+    pos: Duration,
+    track_number: Option<u32>,
+    track_duration: Option<Duration>,
     last_tc: TimecodeBreakDown,
+    track_list_index: Option<usize>,
+    player_index: Option<usize>,
+    /// Slot of the current player, selected with F1 to F8.
+    player_slot: Option<usize>,
+    last_assignment: [u8; 2],
     chan: midi::Channel,
     state: State,
     is_muted: bool,
+    /// Whether the player is muted at the system level rather than in the player.
+    is_system_muted: bool,
+    /// Whether the players list is not empty.
+    has_players: bool,
+    /// Last state sent for each button LED.
+    leds: [LedState; button::LED_COUNT],
     fader_state: FaderState,
+    fader_curve: FaderCurve,
     app: Arc<str>,
+    lcd: LcdBuffer,
+    last_lcd: LcdBuffer,
+    jog: JogState,
+    shuttle: ShuttleState,
+    vpots: [VPot; 8],
+    volume: Option<f64>,
+    balance: f64,
+    rate: f64,
+    shuffle: bool,
+    loop_status: LoopStatus,
+    /// Strips bound to the players list in multi-strip mode.
+    strips: Option<Vec<Strip>>,
+    /// Index in the players list of the player bound to the first strip.
+    strip_offset: usize,
 }
 
 impl Mackie {
+    /// Manufacturer id found in the header of the Mackie sysex messages.
+    pub const MACKIE_ID: [u8; 3] = [0x00, 0x00, 0x66];
+
+    pub const LOGIC_CONTROL_ID: u8 = 0x10;
+    pub const LOGIC_CONTROL_EXT_ID: u8 = 0x11;
+    pub const MCU_ID: u8 = 0x14;
+    pub const MCU_EXT_ID: u8 = 0x15;
+
+    /// Device ids queried when the device id is learnt.
+    const KNOWN_IDS: [u8; 4] = [
+        Self::LOGIC_CONTROL_ID,
+        Self::LOGIC_CONTROL_EXT_ID,
+        Self::MCU_ID,
+        Self::MCU_EXT_ID,
+    ];
+
     pub fn new(device_id: u8) -> Self {
         Self {
             device_id,
+            learns_device_id: false,
+            is_keepalive_pending: false,
+            time_mode: TimeMode::default(),
+            pos: Duration::ZERO,
+            track_number: None,
+            track_duration: None,
             last_tc: TimecodeBreakDown::default(),
+            track_list_index: None,
+            player_index: None,
+            player_slot: None,
+            last_assignment: [b' '; 2],
             chan: midi::Channel::default(),
             state: State::Disconnected,
             is_muted: false,
+            is_system_muted: false,
+            has_players: false,
+            leds: [LedState::Off; button::LED_COUNT],
             fader_state: FaderState::Released,
+            fader_curve: FaderCurve::default(),
             app: NO_APP.clone(),
+            lcd: LcdBuffer::default(),
+            last_lcd: LcdBuffer::default(),
+            jog: JogState::default(),
+            shuttle: ShuttleState::Idle,
+            vpots: DEFAULT_VPOTS,
+            volume: None,
+            balance: 0.0,
+            rate: 1.0,
+            shuffle: false,
+            loop_status: LoopStatus::None,
+            strips: None,
+            strip_offset: 0,
+        }
+    }
+
+    /// Builds a `Mackie` which learns its device id.
+    ///
+    /// The Logic Control & MCU device ids, main units & extenders, are queried
+    /// on each connection attempt & the device id is learnt from the first device reply.
+    /// Devices using other ids are only found if they send a QUERY HOST on their own.
+    pub fn new_generic() -> Self {
+        Self {
+            learns_device_id: true,
+            ..Self::new(Self::MCU_ID)
+        }
+    }
+
+    /// Builds a `Mackie` which binds each fader strip to a player.
+    ///
+    /// Each strip controls the volume & mute of a player from the players list.
+    /// The select buttons make the corresponding player the current one.
+    pub fn new_multi_strip(device_id: u8) -> Self {
+        Self {
+            strips: Some(vec![Strip::default(); STRIPS]),
+            ..Self::new(device_id)
         }
     }
+
+    /// Sets the index of the player bound to the first strip in multi-strip mode.
+    ///
+    /// This allows numbering the strips continuously across devices.
+    pub fn with_strip_offset(mut self, strip_offset: usize) -> Self {
+        self.strip_offset = strip_offset;
+        self
+    }
 }
 
 impl crate::ctrl_surf::ControlSurface for Mackie {
@@ -117,11 +465,24 @@ impl crate::ctrl_surf::ControlSurface for Mackie {
         log::debug!("Attempt to connect to device {:#02x}", self.device_id);
 
         self.state = State::Connecting(ConnectionStatus::DeviceQueried);
+        self.is_keepalive_pending = false;
+
+        let mut list = if self.learns_device_id {
+            // The device only answers queries for its own id.
+            Self::KNOWN_IDS
+                .iter()
+                .map(|&device_id| {
+                    let mut payload = self.payload_for(QUERY_DEVICE);
+                    payload[3] = device_id;
+                    midi::Msg::new_sysex(&payload).to_device()
+                })
+                .collect()
+        } else {
+            vec![midi::Msg::new_sysex(&self.payload_for(QUERY_DEVICE)).to_device()]
+        };
+        list.push(Msg::connetion_in_progress());
 
-        vec![
-            midi::Msg::new_sysex(&self.payload_for(QUERY_DEVICE)).to_device(),
-            Msg::connetion_in_progress(),
-        ]
+        list
     }
 
     fn abort_connection(&mut self) -> Vec<Msg> {
@@ -135,19 +496,40 @@ impl crate::ctrl_surf::ControlSurface for Mackie {
     }
 
     fn msg_from_device(&mut self, msg: crate::midi::Msg) -> Vec<Msg> {
+        // Any message proves that the device is still there.
+        self.is_keepalive_pending = false;
+
         let buf = msg.inner();
 
         if let Some(&tag_chan) = buf.first() {
-            self.chan = midi::Channel::from(tag_chan);
+            let chan = midi::Channel::from(tag_chan);
+            // In multi-strip mode, the channel identifies the fader strip.
+            if self.strips.is_none() {
+                self.chan = chan;
+            }
 
             match midi::Tag::from_tag_chan(tag_chan) {
                 button::TAG => {
                     if let Some(id_value) = buf.get(1..=2) {
                         use button::*;
                         use Mixer::*;
+                        use ShuttleDirection::*;
                         use Transport::*;
 
                         match id_value {
+                            [id @ MUTE..=MUTE_LAST, PRESSED] if self.strips.is_some() => {
+                                return self.device_strip_mute((id - MUTE) as usize);
+                            }
+                            [id @ SELECT_FIRST..=SELECT_LAST, PRESSED] if self.strips.is_some() => {
+                                let index = self.strip_offset + (id - SELECT_FIRST) as usize;
+                                return CtrlSurfEvent::SelectPlayer(index).to_app().into();
+                            }
+                            [id @ FADER_TOUCHED..=FADER_TOUCHED_LAST, value]
+                                if self.strips.is_some() =>
+                            {
+                                let index = (id - FADER_TOUCHED) as usize;
+                                return self.device_strip_fader_touch(index, *value);
+                            }
                             [MUTE, PRESSED] => {
                                 if self.is_muted {
                                     return Unmute.to_app().into();
@@ -155,17 +537,52 @@ impl crate::ctrl_surf::ControlSurface for Mackie {
                                     return Mute.to_app().into();
                                 }
                             }
-                            [PREVIOUS, PRESSED] => return Previous.to_app().into(),
-                            [NEXT, PRESSED] => return Next.to_app().into(),
+                            [SOLO, PRESSED] => return SetShuffle(!self.shuffle).to_app().into(),
+                            [CYCLE, PRESSED] => {
+                                return SetLoopStatus(self.loop_status.next()).to_app().into();
+                            }
+                            [MARKER, PRESSED] => return CtrlSurfEvent::Marker.to_app().into(),
+                            [id @ F1..=F8, PRESSED] => {
+                                let slot = (id - F1) as usize;
+                                return CtrlSurfEvent::SelectPlayerSlot(slot).to_app().into();
+                            }
+                            [REC, PRESSED] => return self.device_toggle_loop(LoopStatus::Track),
+                            [SELECT_FIRST, PRESSED] => {
+                                return self.device_toggle_loop(LoopStatus::Playlist);
+                            }
+                            [PREVIOUS, PRESSED] => return self.device_shuttle_pressed(Backward),
+                            [PREVIOUS, RELEASED] => return self.device_shuttle_released(Backward),
+                            [NEXT, PRESSED] => return self.device_shuttle_pressed(Forward),
+                            [NEXT, RELEASED] => return self.device_shuttle_released(Forward),
                             [STOP, PRESSED] => return Stop.to_app().into(),
                             [PLAY, PRESSED] => return PlayPause.to_app().into(),
                             [FADER_TOUCHED, value] => return self.device_fader_touch(*value),
+                            [SMPTE_BEATS, PRESSED] => return self.device_next_time_mode(),
+                            [id @ VPOT_PUSH_FIRST..=VPOT_PUSH_LAST, PRESSED] => {
+                                return self.device_vpot_pushed((id - VPOT_PUSH_FIRST) as usize)
+                            }
                             _ => (),
                         }
                     }
                 }
+                encoder::TAG => {
+                    use encoder::*;
+
+                    match buf.get(1..=2) {
+                        Some(&[JOG, value]) => return self.device_jog(value),
+                        Some(&[id @ VPOT_FIRST..=VPOT_LAST, value]) => {
+                            return self.device_vpot((id - VPOT_FIRST) as usize, value);
+                        }
+                        _ => (),
+                    }
+                }
                 fader::TAG => {
                     if let Some(value) = buf.get(1..=2) {
+                        if self.strips.is_some() {
+                            let index = u8::from(chan) as usize;
+                            return self.device_strip_fader_moved(index, value);
+                        }
+
                         return self.device_fader_moved(value);
                     }
                 }
@@ -190,10 +607,7 @@ impl crate::ctrl_surf::ControlSurface for Mackie {
                 match event {
                     Play => return self.app_play(),
                     Pause => return self.app_pause(),
-                    Stop => {
-                        // FIXME go offline
-                        return self.reset();
-                    }
+                    Stop => return self.reset(),
                     _ => (),
                 }
             }
@@ -203,17 +617,26 @@ impl crate::ctrl_surf::ControlSurface for Mackie {
                     Volume(vol) => return self.app_volume(vol),
                     Mute => return self.app_mute(),
                     Unmute => return self.app_unmute(),
+                    Balance(balance) => return self.app_balance(balance),
                 }
             }
+            Strip(index, mixer) => return self.app_strip(index, mixer),
             NewApp(app) => {
                 let msg_list = if app != self.app {
                     log::debug!("New application {app}");
 
+                    // Strip names are displayed on first row in multi-strip mode.
+                    if self.strips.is_none() {
+                        self.lcd.set_row(0, &app);
+                    }
+                    self.lcd.set_row(1, "");
                     self.app = app;
                     self.state = State::PendingAppData;
-                    // FIXME send player name to device
 
-                    CtrlSurfEvent::DataRequest.to_app().into()
+                    let mut list = self.lcd_update();
+                    list.push(CtrlSurfEvent::DataRequest.to_app());
+
+                    list
                 } else {
                     Msg::none()
                 };
@@ -229,7 +652,16 @@ impl crate::ctrl_surf::ControlSurface for Mackie {
 
                 match data {
                     Position(pos) => return self.app_position(pos),
-                    Track(_) => (),
+                    Track(track) => return self.app_track(&track),
+                    PlayerIndex(index) => return self.app_player_index(index),
+                    PlayerSlot(slot) => return self.app_player_slot(slot),
+                    PlayerList(list) => return self.app_player_list(&list),
+                    Rate(rate) => return self.app_rate(rate),
+                    Shuffle(shuffle) => return self.app_shuffle(shuffle),
+                    LoopStatus(status) => return self.app_loop_status(status),
+                    Markers(markers) => return self.app_markers(markers),
+                    Level(level) => return self.app_level(level),
+                    SystemMuted(is_muted) => return self.app_system_muted(is_muted),
                     PlaybackStatus(status) => {
                         use crate::ctrl_surf::data::PlaybackStatus::*;
 
@@ -246,44 +678,158 @@ impl crate::ctrl_surf::ControlSurface for Mackie {
         Msg::none()
     }
 
+    fn keepalive(&mut self) -> Vec<Msg> {
+        if !self.is_connected() {
+            return Msg::none();
+        }
+
+        if self.is_keepalive_pending {
+            log::warn!("Device {:#02x} stopped answering", self.device_id);
+            self.is_keepalive_pending = false;
+            self.state = State::Disconnected;
+
+            return Msg::connection_lost().into();
+        }
+
+        // The device answers with a QUERY_HOST.
+        self.is_keepalive_pending = true;
+        midi::Msg::new_sysex(&self.payload_for(connection::QUERY_DEVICE))
+            .to_device()
+            .into()
+    }
+
+    fn set_fader_curve(&mut self, curve: FaderCurve) -> Vec<Msg> {
+        self.fader_curve = curve;
+
+        // Move the faders to the positions matching the volumes with the new curve.
+        let strips = match self.strips.as_ref() {
+            Some(strips) => strips,
+            None => {
+                return match (self.volume, self.fader_state) {
+                    (Some(vol), FaderState::Released) => self.build_fader_msg(vol).into(),
+                    _ => Msg::none(),
+                };
+            }
+        };
+
+        strips
+            .iter()
+            .enumerate()
+            .filter_map(|(index, strip)| match (strip.volume, strip.fader_state) {
+                (Some(vol), FaderState::Released) => {
+                    Some(Self::build_strip_fader_msg(curve, index, vol))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
     fn is_connected(&self) -> bool {
         !matches!(self.state, State::Connecting(_) | State::Disconnected)
     }
 
+    fn tick(&mut self) -> Vec<Msg> {
+        self.shuttle_tick()
+    }
+
     fn reset(&mut self) -> Vec<Msg> {
         use button::*;
         use display_7_seg::*;
         use State::*;
 
-        let mut list = Vec::new();
-
-        let tag_chan = button::TAG | self.chan;
-        list.push([tag_chan, MUTE, OFF].into());
-        list.push([tag_chan, PREVIOUS, OFF].into());
-        list.push([tag_chan, NEXT, OFF].into());
-        list.push([tag_chan, STOP, OFF].into());
-        list.push([tag_chan, PLAY, OFF].into());
+        let mut list = vec![
+            self.led(MUTE, LedState::Off),
+            self.led(PREVIOUS, LedState::Off),
+            self.led(NEXT, LedState::Off),
+            self.led(STOP, self.stop_led()),
+            self.led(PLAY, LedState::Off),
+        ];
 
         for idx in 0..10 {
             list.push([display_7_seg::TAG.into(), TIME_LEFT_DIGIT - idx as u8, b' '].into());
         }
+        for idx in 0..2 {
+            list.push([display_7_seg::TAG.into(), ASSIGNMENT_LEFT_DIGIT - idx, b' '].into());
+        }
+        list.extend(self.time_mode_leds());
+
+        for idx in 0..self.vpots.len() {
+            list.push([encoder::TAG.into(), encoder::VPOT_RING_FIRST + idx as u8, 0].into());
+        }
+        let meters = self.strips.as_ref().map_or(1, Vec::len);
+        list.extend((0..meters).map(|index| Self::meter_msg(index, 0.0)));
+
+        self.shuffle = false;
+        self.loop_status = LoopStatus::None;
+        list.extend(self.shuffle_loop_leds());
+        list.push(self.led(MARKER, LedState::Off));
+
+        if let Some(strips) = self.strips.as_mut() {
+            strips.fill(Strip::default());
+
+            for index in 0..STRIPS as u8 {
+                list.push(self.led(MUTE + index, LedState::Off));
+                list.push(self.led(SELECT_FIRST + index, LedState::Off));
+            }
+            list.extend(
+                (0..STRIPS).map(|index| Self::build_strip_fader_msg(self.fader_curve, index, 0.0)),
+            );
+        }
+
+        self.lcd = LcdBuffer::default();
+        list.extend(self.lcd_update());
 
         self.state = match self.state {
             Connected | Playing | Stopped => Connected,
             other => other,
         };
         self.is_muted = false;
+        self.is_system_muted = false;
+        self.shuttle = ShuttleState::Idle;
+        self.pos = Duration::ZERO;
+        self.track_number = None;
+        self.track_duration = None;
         self.last_tc = TimecodeBreakDown::default();
+        self.track_list_index = None;
+        self.player_index = None;
+        self.last_assignment = [b' '; 2];
+        self.volume = None;
+        self.balance = 0.0;
+        self.rate = 1.0;
         self.app = NO_APP.clone();
 
         list
     }
+
+    fn disconnect(&mut self) -> Vec<Msg> {
+        if !self.is_connected() {
+            return Msg::none();
+        }
+
+        log::debug!("Disconnecting from device {:#02x}", self.device_id);
+
+        let mut list = self.reset();
+
+        // Some devices don't support RESET_LEDS, so switch them off one by one.
+        // Don't record these so the LED states can be restored on reconnection.
+        let tag_chan = button::TAG | self.chan;
+        list.extend((0..=button::LAST_LED).map(|led| Msg::from([tag_chan, led, button::OFF])));
+        if self.strips.is_none() {
+            list.push(self.build_fader_msg(0.0));
+        }
+        list.push(midi::Msg::new_sysex(&self.payload_for(connection::GO_OFFLINE)).to_device());
+
+        self.state = State::Disconnected;
+
+        list
+    }
 }
 
 /// Device events.
 impl Mackie {
     fn build_fader_msg(&self, vol: f64) -> Msg {
-        let two_bytes = midi::normalized_f64::to_be(vol).unwrap();
+        let pos = self.fader_curve.to_position(vol);
+        let two_bytes = midi::normalized_f64::to_be(pos).unwrap();
         [fader::TAG | self.chan, two_bytes[0], two_bytes[1]].into()
     }
 
@@ -313,7 +859,7 @@ impl Mackie {
         use Mixer::*;
 
         let vol = match midi::normalized_f64::from_be(buf) {
-            Ok(value) => value,
+            Ok(pos) => self.fader_curve.to_volume(pos),
             Err(err) => {
                 log::error!("Fader moved value: {err}");
                 return Msg::none();
@@ -337,132 +883,844 @@ impl Mackie {
     }
 }
 
-/// App events.
+/// Jog wheel.
 impl Mackie {
-    fn app_mute(&mut self) -> Vec<Msg> {
-        use button::*;
+    fn device_jog(&mut self, value: u8) -> Vec<Msg> {
+        use Transport::*;
 
-        self.is_muted = true;
-        Msg::from([TAG | self.chan, MUTE, ON]).into()
-    }
+        let ticks = relative_ticks(value).unsigned_abs();
+        if ticks == 0 {
+            return Msg::none();
+        }
 
-    fn app_unmute(&mut self) -> Vec<Msg> {
-        use button::*;
+        // Accelerate when the wheel keeps spinning fast.
+        let now = Instant::now();
+        match self.jog.last {
+            Some(last) if now.duration_since(last) < JOG_ACCEL_WINDOW => {
+                self.jog.fast_ticks = self.jog.fast_ticks.saturating_add(1);
+            }
+            _ => self.jog.fast_ticks = 0,
+        }
+        self.jog.last = Some(now);
 
-        self.is_muted = false;
-        Msg::from([TAG | self.chan, MUTE, OFF]).into()
+        let accel = (1 + self.jog.fast_ticks / JOG_ACCEL_RAMP).min(JOG_MAX_ACCEL);
+        let steps = ticks * accel;
+
+        if value & encoder::CCW == 0 {
+            StepForward(steps).to_app().into()
+        } else {
+            StepBackward(steps).to_app().into()
+        }
     }
+}
 
-    fn app_play(&mut self) -> Vec<Msg> {
-        use button::*;
-        use State::*;
+/// V-Pots.
+impl Mackie {
+    fn device_vpot(&mut self, idx: usize, value: u8) -> Vec<Msg> {
+        let delta = relative_ticks(value) as f64;
+
+        let func = self.vpots[idx].func;
+        let mut list: Vec<Msg> = match func {
+            VPotFunc::None => return Msg::none(),
+            VPotFunc::Volume => {
+                let cur_vol = match self.volume {
+                    Some(vol) => vol,
+                    None => {
+                        log::debug!("Ignoring V-Pot volume change: volume unknown");
+                        return Msg::none();
+                    }
+                };
+                let vol = (cur_vol + delta * VPOT_VOLUME_STEP).clamp(0.0, 1.0);
+                self.volume = Some(vol);
 
-        let mut list = Vec::new();
-        let tag_chan = button::TAG | self.chan;
+                Mixer::Volume(vol).to_app().into()
+            }
+            VPotFunc::Balance => {
+                self.balance = (self.balance + delta * VPOT_BALANCE_STEP).clamp(-1.0, 1.0);
 
-        match self.state {
-            Connected | PendingAppData | Stopped => {
-                self.state = Playing;
-                list.push([tag_chan, STOP, OFF].into());
+                Mixer::Balance(self.balance).to_app().into()
             }
-            Playing => (),
-            Connecting(_) | Disconnected => unreachable!(),
-        }
+            VPotFunc::Rate => {
+                let log_rate = self.rate.log2() + delta * VPOT_RATE_LOG_STEP;
+                self.rate = log_rate.exp2().clamp(RATE_MIN, RATE_MAX);
 
-        list.push([tag_chan, PLAY, ON].into());
+                Transport::SetRate(self.rate).to_app().into()
+            }
+        };
+
+        list.extend(self.vpot_rings_update(func));
 
         list
     }
 
-    fn app_pause(&mut self) -> Vec<Msg> {
-        use button::*;
-        use State::*;
-
-        let mut list = Vec::new();
-        let tag_chan = button::TAG | self.chan;
-
-        match self.state {
-            Connected | PendingAppData | Playing => {
-                self.state = Stopped;
-                list.push([tag_chan, PLAY, OFF].into());
-            }
-            Stopped => (),
-            Connecting(_) | Disconnected => unreachable!(),
+    fn device_vpot_pushed(&mut self, idx: usize) -> Vec<Msg> {
+        let vpot = &mut self.vpots[idx];
+        if vpot.func == VPotFunc::None {
+            return Msg::none();
         }
 
-        list.push([tag_chan, STOP, ON].into());
+        vpot.ring = vpot.ring.next();
+        log::debug!("V-Pot {idx} ring mode {:?}", vpot.ring);
 
-        list
+        self.vpot_ring_msg(idx).into()
     }
 
-    fn app_volume(&mut self, vol: f64) -> Vec<Msg> {
-        use FaderState::*;
+    fn app_balance(&mut self, balance: f64) -> Vec<Msg> {
+        self.balance = balance;
+        self.vpot_rings_update(VPotFunc::Balance)
+    }
 
-        match &mut self.fader_state {
-            Released => self.build_fader_msg(vol).into(),
-            Touched { last_volume } => {
-                // user touches fader => don't move it before it's released.
-                *last_volume = Some(vol);
+    fn app_rate(&mut self, rate: f64) -> Vec<Msg> {
+        self.rate = rate;
+        self.vpot_rings_update(VPotFunc::Rate)
+    }
 
-                Msg::none()
-            }
+    fn app_level(&mut self, level: f64) -> Vec<Msg> {
+        match self.meter_strip_index() {
+            Some(index) => Self::meter_msg(index, level).into(),
+            None => Msg::none(),
         }
     }
 
-    fn app_position(&mut self, pos: Duration) -> Vec<Msg> {
-        use display_7_seg::*;
+    /// Returns the index of the strip displaying the current player's meter.
+    ///
+    /// This is the first strip, unless in multi-strip mode, in which case it is
+    /// the current player's strip if it is on this device.
+    fn meter_strip_index(&self) -> Option<usize> {
+        match self.strips {
+            Some(ref strips) => self
+                .player_index?
+                .checked_sub(self.strip_offset)
+                .filter(|&index| index < strips.len()),
+            None => Some(0),
+        }
+    }
 
-        let mut list = Vec::new();
-        let tc = TimecodeBreakDown::from(Timecode::from(pos));
+    /// Builds the meter message for the strip at `index`.
+    ///
+    /// `level` is a linear peak value which is displayed on a dB scale.
+    fn meter_msg(index: usize, level: f64) -> Msg {
+        let db = 20.0 * level.max(f64::MIN_POSITIVE).log10();
+        let value = ((db - meter::FLOOR_DB) / -meter::FLOOR_DB * meter::MAX as f64)
+            .round()
+            .clamp(0.0, meter::MAX as f64) as u8;
+
+        // Strip index goes in the high nibble.
+        [meter::TAG.into(), ((index as u8) << 4) | value].into()
+    }
 
-        for (idx, (&last_digit, digit)) in self.last_tc.0.iter().zip(tc.0).enumerate() {
-            if last_digit != digit {
-                list.push([TAG.into(), TIME_LEFT_DIGIT - idx as u8, digit].into());
-            }
-        }
+    /// Updates the LED rings of the V-Pots assigned to `func`.
+    fn vpot_rings_update(&self, func: VPotFunc) -> Vec<Msg> {
+        (0..self.vpots.len())
+            .filter(|&idx| self.vpots[idx].func == func)
+            .map(|idx| self.vpot_ring_msg(idx))
+            .collect()
+    }
 
-        self.last_tc = tc;
+    fn vpot_ring_msg(&self, idx: usize) -> Msg {
+        let vpot = &self.vpots[idx];
+
+        let value = match vpot.func {
+            VPotFunc::None => 0,
+            VPotFunc::Volume => match self.volume {
+                Some(vol) => vpot.ring.ring_value(vol),
+                None => 0,
+            },
+            VPotFunc::Balance => vpot.ring.ring_value((self.balance + 1.0) / 2.0),
+            VPotFunc::Rate => {
+                let range = RATE_MAX.log2() - RATE_MIN.log2();
+                vpot.ring
+                    .ring_value((self.rate.log2() - RATE_MIN.log2()) / range)
+            }
+        };
 
-        list
+        [
+            encoder::TAG.into(),
+            encoder::VPOT_RING_FIRST + idx as u8,
+            value,
+        ]
+        .into()
     }
 }
 
-/// Device handshake.
+/// Shuttle.
 impl Mackie {
-    fn device_sysex(&mut self, msg: midi::Msg) -> Vec<Msg> {
-        self.device_connection(msg)
-            .unwrap_or_else(|err| Msg::from_connection_result(Err(err)).into())
+    fn device_shuttle_pressed(&mut self, dir: ShuttleDirection) -> Vec<Msg> {
+        self.shuttle = ShuttleState::Pressed {
+            dir,
+            since: Instant::now(),
+        };
+
+        Msg::schedule_tick(SHUTTLE_HOLD_DELAY).into()
     }
 
-    fn device_connection(&mut self, msg: midi::Msg) -> Result<Vec<Msg>, Error> {
-        use crate::bytes::Displayable;
-        use connection::*;
-        use Error::*;
+    fn device_shuttle_released(&mut self, released_dir: ShuttleDirection) -> Vec<Msg> {
+        use ShuttleState::*;
+        use Transport::*;
 
-        let payload = msg.parse_sysex()?;
+        match self.shuttle {
+            Pressed { dir, .. } if dir == released_dir => {
+                self.shuttle = Idle;
+                match dir {
+                    ShuttleDirection::Backward => Previous.to_app().into(),
+                    ShuttleDirection::Forward => Next.to_app().into(),
+                }
+            }
+            Shuttling { dir, .. } if dir == released_dir => {
+                log::debug!("Stopping shuttle {dir:?}");
+                self.shuttle = Idle;
 
-        // Check header
-        if payload.len() < 5 {
+                self.led(Self::shuttle_button(dir), LedState::Off).into()
+            }
+            _ => Msg::none(),
+        }
+    }
+
+    fn shuttle_tick(&mut self) -> Vec<Msg> {
+        use ShuttleState::*;
+        use Transport::*;
+
+        let mut list = Vec::new();
+
+        let (dir, steps) = match self.shuttle {
+            Idle => return Msg::none(),
+            Pressed { dir, since } => {
+                let elapsed = since.elapsed();
+                if elapsed < SHUTTLE_HOLD_DELAY {
+                    return Msg::schedule_tick(SHUTTLE_HOLD_DELAY - elapsed).into();
+                }
+
+                log::debug!("Starting shuttle {dir:?}");
+                self.shuttle = Shuttling {
+                    dir,
+                    since: Instant::now(),
+                };
+                // Blink while seeking.
+                list.push(self.led(Self::shuttle_button(dir), LedState::Blink));
+
+                (dir, 1)
+            }
+            Shuttling { dir, since } => {
+                // Speed up as the button is held.
+                let accel = since.elapsed().as_millis() / SHUTTLE_ACCEL_PERIOD.as_millis();
+                let steps = (1 + accel as u16).min(SHUTTLE_MAX_STEPS);
+
+                (dir, steps)
+            }
+        };
+
+        list.push(match dir {
+            ShuttleDirection::Backward => StepBackward(steps).to_app(),
+            ShuttleDirection::Forward => StepForward(steps).to_app(),
+        });
+        list.push(Msg::schedule_tick(SHUTTLE_INTERVAL));
+
+        list
+    }
+
+    fn shuttle_button(dir: ShuttleDirection) -> u8 {
+        match dir {
+            ShuttleDirection::Backward => button::PREVIOUS,
+            ShuttleDirection::Forward => button::NEXT,
+        }
+    }
+}
+
+/// Shuffle, loop & A-B loop markers.
+///
+/// - MARKER sets the A then B markers, and clears them on third press.
+/// - SOLO toggles shuffle.
+/// - CYCLE cycles through the loop statuses. Its LED blinks when looping
+///   the track and is lit when looping the playlist.
+/// - REC toggles looping the track.
+/// - SELECT toggles looping the playlist, except in multi-strip mode.
+impl Mackie {
+    fn device_toggle_loop(&self, status: LoopStatus) -> Vec<Msg> {
+        let status = if self.loop_status == status {
+            LoopStatus::None
+        } else {
+            status
+        };
+
+        Transport::SetLoopStatus(status).to_app().into()
+    }
+
+    fn app_shuffle(&mut self, shuffle: bool) -> Vec<Msg> {
+        self.shuffle = shuffle;
+        self.shuffle_loop_leds()
+    }
+
+    fn app_loop_status(&mut self, status: LoopStatus) -> Vec<Msg> {
+        self.loop_status = status;
+        self.shuffle_loop_leds()
+    }
+
+    /// MARKER blinks while waiting for B and is lit once the A-B loop is set.
+    fn app_markers(&mut self, markers: Markers) -> Vec<Msg> {
+        let state = match (markers.a, markers.b) {
+            (Some(_), Some(_)) => LedState::On,
+            (Some(_), None) => LedState::Blink,
+            _ => LedState::Off,
+        };
+
+        self.led(button::MARKER, state).into()
+    }
+
+    fn shuffle_loop_leds(&mut self) -> Vec<Msg> {
+        use button::*;
+
+        let cycle = match self.loop_status {
+            LoopStatus::None => LedState::Off,
+            LoopStatus::Track => LedState::Blink,
+            LoopStatus::Playlist => LedState::On,
+        };
+
+        let mut list = vec![
+            self.led(SOLO, self.shuffle.into()),
+            self.led(CYCLE, cycle),
+            self.led(REC, (self.loop_status == LoopStatus::Track).into()),
+        ];
+        if self.strips.is_none() {
+            let is_looping_playlist = self.loop_status == LoopStatus::Playlist;
+            list.push(self.led(SELECT_FIRST, is_looping_playlist.into()));
+        }
+
+        list
+    }
+}
+
+/// App events.
+impl Mackie {
+    fn app_mute(&mut self) -> Vec<Msg> {
+        use button::*;
+
+        self.is_muted = true;
+        if let Some(index) = self.cur_strip_index() {
+            return self.app_strip(index, Mixer::Mute);
+        }
+
+        self.led(MUTE, self.mute_led_state()).into()
+    }
+
+    fn app_unmute(&mut self) -> Vec<Msg> {
+        use button::*;
+
+        self.is_muted = false;
+        if let Some(index) = self.cur_strip_index() {
+            return self.app_strip(index, Mixer::Unmute);
+        }
+
+        self.led(MUTE, self.mute_led_state()).into()
+    }
+
+    fn app_system_muted(&mut self, is_muted: bool) -> Vec<Msg> {
+        self.is_system_muted = is_muted;
+
+        if is_muted || self.is_muted {
+            self.app_mute()
+        } else {
+            self.app_unmute()
+        }
+    }
+
+    /// MUTE blinks when muted at the system level.
+    fn mute_led_state(&self) -> LedState {
+        match (self.is_muted, self.is_system_muted) {
+            (true, true) => LedState::Blink,
+            (is_muted, _) => is_muted.into(),
+        }
+    }
+
+    fn app_play(&mut self) -> Vec<Msg> {
+        use button::*;
+        use State::*;
+
+        let mut list = Vec::new();
+
+        match self.state {
+            Connected | PendingAppData | Stopped => {
+                self.state = Playing;
+                list.push(self.led(STOP, LedState::Off));
+            }
+            Playing => (),
+            Connecting(_) | Disconnected => unreachable!(),
+        }
+
+        list.push(self.led(PLAY, LedState::On));
+
+        list
+    }
+
+    fn app_pause(&mut self) -> Vec<Msg> {
+        use button::*;
+        use State::*;
+
+        let mut list = Vec::new();
+
+        match self.state {
+            Connected | PendingAppData | Playing => {
+                self.state = Stopped;
+                list.push(self.led(STOP, LedState::Off));
+            }
+            Stopped => (),
+            Connecting(_) | Disconnected => unreachable!(),
+        }
+
+        // PLAY blinks while paused.
+        list.push(self.led(PLAY, LedState::Blink));
+
+        list
+    }
+
+    fn app_volume(&mut self, vol: f64) -> Vec<Msg> {
+        use FaderState::*;
+
+        self.volume = Some(vol);
+        let mut list = self.vpot_rings_update(VPotFunc::Volume);
+
+        if self.strips.is_some() {
+            if let Some(index) = self.player_index {
+                list.extend(self.app_strip(index, Mixer::Volume(vol)));
+            }
+
+            return list;
+        }
+
+        match &mut self.fader_state {
+            Released => list.push(self.build_fader_msg(vol)),
+            Touched { last_volume } => {
+                // user touches fader => don't move it before it's released.
+                *last_volume = Some(vol);
+            }
+        }
+
+        list
+    }
+
+    fn app_track(&mut self, track: &ctrl_surf::Track) -> Vec<Msg> {
+        self.track_number = track.number;
+        self.track_duration = track.duration;
+        self.track_list_index = track.list_index;
+        let mut list = self.timecode_update();
+        list.extend(self.assignment_update());
+
+        let artist = track.artist.as_deref();
+        let title = track.title.as_deref();
+
+        match (artist, title) {
+            (Some(artist), Some(title)) => self.lcd.set_row(1, &format!("{artist} - {title}")),
+            (Some(text), None) | (None, Some(text)) => self.lcd.set_row(1, text),
+            (None, None) => self.lcd.set_row(1, ""),
+        }
+        list.extend(self.lcd_update());
+
+        list
+    }
+
+    fn app_position(&mut self, pos: Duration) -> Vec<Msg> {
+        self.pos = pos;
+        self.timecode_update()
+    }
+}
+
+/// Multi-strip mode.
+impl Mackie {
+    fn cur_strip_index(&self) -> Option<usize> {
+        self.strips.as_ref().and(self.player_index)
+    }
+
+    fn build_strip_fader_msg(curve: FaderCurve, index: usize, vol: f64) -> Msg {
+        let two_bytes = midi::normalized_f64::to_be(curve.to_position(vol)).unwrap();
+        let chan = midi::Channel::from(index as u8);
+        [fader::TAG | chan, two_bytes[0], two_bytes[1]].into()
+    }
+
+    fn device_strip_mute(&mut self, index: usize) -> Vec<Msg> {
+        use Mixer::*;
+
+        let strip = match self.strips.as_ref().and_then(|strips| strips.get(index)) {
+            Some(strip) if strip.name.is_some() => strip,
+            _ => return Msg::none(),
+        };
+
+        let event = if strip.is_muted { Unmute } else { Mute };
+        CtrlSurfEvent::Strip(self.strip_offset + index, event)
+            .to_app()
+            .into()
+    }
+
+    fn device_strip_fader_touch(&mut self, index: usize, value: u8) -> Vec<Msg> {
+        use FaderState::*;
+
+        let strip = match self
+            .strips
+            .as_mut()
+            .and_then(|strips| strips.get_mut(index))
+        {
+            Some(strip) => strip,
+            None => return Msg::none(),
+        };
+
+        let is_touched = value > fader::TOUCH_THRSD;
+        match strip.fader_state {
+            Released if is_touched => {
+                strip.fader_state = Touched { last_volume: None };
+            }
+            Touched { last_volume } if !is_touched => {
+                strip.fader_state = Released;
+                if let Some(vol) = last_volume {
+                    return vec![
+                        CtrlSurfEvent::Strip(self.strip_offset + index, Mixer::Volume(vol))
+                            .to_app(),
+                        Self::build_strip_fader_msg(self.fader_curve, index, vol),
+                    ];
+                }
+            }
+            _ => (),
+        }
+
+        Msg::none()
+    }
+
+    fn device_strip_fader_moved(&mut self, index: usize, buf: &[u8]) -> Vec<Msg> {
+        use FaderState::*;
+
+        let vol = match midi::normalized_f64::from_be(buf) {
+            Ok(pos) => self.fader_curve.to_volume(pos),
+            Err(err) => {
+                log::error!("Strip {index} fader moved value: {err}");
+                return Msg::none();
+            }
+        };
+
+        let strip = match self
+            .strips
+            .as_mut()
+            .and_then(|strips| strips.get_mut(index))
+        {
+            Some(strip) => strip,
+            None => return Msg::none(),
+        };
+
+        match &mut strip.fader_state {
+            Touched { last_volume } if strip.name.is_some() => {
+                *last_volume = Some(vol);
+                CtrlSurfEvent::Strip(self.strip_offset + index, Mixer::Volume(vol))
+                    .to_app()
+                    .into()
+            }
+            Touched { .. } => Msg::none(),
+            Released => {
+                log::warn!("Strip {index} fader moved but no touch detected => ignoring");
+
+                Msg::none()
+            }
+        }
+    }
+
+    fn app_strip(&mut self, index: usize, mixer: Mixer) -> Vec<Msg> {
+        use button::*;
+        use FaderState::*;
+        use Mixer::*;
+
+        let mute_state = if self.is_system_muted && Some(index) == self.player_index {
+            LedState::Blink
+        } else {
+            LedState::On
+        };
+
+        // Get the index relative to this device's strips.
+        let index = match index.checked_sub(self.strip_offset) {
+            Some(index) => index,
+            None => return Msg::none(),
+        };
+        let strip = match self
+            .strips
+            .as_mut()
+            .and_then(|strips| strips.get_mut(index))
+        {
+            Some(strip) => strip,
+            None => return Msg::none(),
+        };
+
+        match mixer {
+            Volume(vol) => match &mut strip.fader_state {
+                Released => {
+                    strip.volume = Some(vol);
+                    Self::build_strip_fader_msg(self.fader_curve, index, vol).into()
+                }
+                Touched { last_volume } => {
+                    strip.volume = Some(vol);
+                    // user touches fader => don't move it before it's released.
+                    *last_volume = Some(vol);
+                    Msg::none()
+                }
+            },
+            Mute => {
+                strip.is_muted = true;
+                self.led(MUTE + index as u8, mute_state).into()
+            }
+            Unmute => {
+                strip.is_muted = false;
+                self.led(MUTE + index as u8, LedState::Off).into()
+            }
+            Balance(_) => Msg::none(),
+        }
+    }
+
+    /// Binds the strips to the players and displays their names.
+    fn app_player_list(&mut self, list: &[Arc<str>]) -> Vec<Msg> {
+        use button::*;
+
+        let mut msg_list = Vec::new();
+
+        let has_players = !list.is_empty();
+        if has_players != self.has_players {
+            self.has_players = has_players;
+            msg_list.push(self.led(STOP, self.stop_led()));
+        }
+
+        let strips = match self.strips.as_mut() {
+            Some(strips) => strips,
+            None => return msg_list,
+        };
+
+        let mut released = Vec::new();
+        for (index, strip) in strips.iter_mut().enumerate() {
+            let name = list.get(self.strip_offset + index).cloned();
+            self.lcd.set_strip(0, index, name.as_deref().unwrap_or(""));
+
+            if name.is_none() && strip.name.is_some() {
+                // No more players for this strip.
+                *strip = Strip::default();
+                released.push(index);
+            }
+            strip.name = name;
+        }
+
+        for index in released {
+            msg_list.push(self.led(MUTE + index as u8, LedState::Off));
+            msg_list.push(Self::build_strip_fader_msg(self.fader_curve, index, 0.0));
+        }
+
+        msg_list.extend(self.select_leds());
+        msg_list.extend(self.lcd_update());
+
+        msg_list
+    }
+
+    /// Lights the select button of the current player's strip.
+    fn select_leds(&mut self) -> Vec<Msg> {
+        use button::*;
+
+        if self.strips.is_none() {
+            return Msg::none();
+        }
+
+        (0..STRIPS)
+            .map(|index| {
+                let is_cur = Some(self.strip_offset + index) == self.player_index;
+                self.led(SELECT_FIRST + index as u8, is_cur.into())
+            })
+            .collect()
+    }
+}
+
+/// Player slots.
+///
+/// F1 to F8 make the player assigned to the corresponding slot the current one.
+/// The LED of the current player's slot is lit.
+impl Mackie {
+    fn app_player_slot(&mut self, slot: Option<usize>) -> Vec<Msg> {
+        use button::*;
+
+        let mut list = Vec::new();
+        if let Some(prev) = self.player_slot.take() {
+            list.push(self.led(F1 + prev as u8, LedState::Off));
+        }
+
+        if let Some(slot) = slot.filter(|&slot| slot <= (F8 - F1) as usize) {
+            self.player_slot = Some(slot);
+            list.push(self.led(F1 + slot as u8, LedState::On));
+        }
+
+        list
+    }
+}
+
+/// Button LEDs.
+impl Mackie {
+    /// STOP blinks while no player is available.
+    fn stop_led(&self) -> LedState {
+        if self.has_players {
+            LedState::Off
+        } else {
+            LedState::Blink
+        }
+    }
+
+    /// Sets the state of the LED `id` and builds the corresponding message.
+    fn led(&mut self, id: u8, state: LedState) -> Msg {
+        if let Some(led) = self.leds.get_mut(id as usize) {
+            *led = state;
+        }
+
+        [button::TAG | self.chan, id, state.value()].into()
+    }
+
+    /// Restores the LEDs which are not off, since the device state is unknown
+    /// after a (re)connection.
+    fn leds_restore(&self) -> Vec<Msg> {
+        let tag_chan = button::TAG | self.chan;
+        self.leds
+            .iter()
+            .enumerate()
+            .filter(|(_, state)| **state != LedState::Off)
+            .map(|(id, state)| Msg::from([tag_chan, id as u8, state.value()]))
+            .collect()
+    }
+}
+
+/// Assignment display.
+impl Mackie {
+    fn app_player_index(&mut self, index: usize) -> Vec<Msg> {
+        self.player_index = Some(index);
+
+        let mut list = self.assignment_update();
+        list.extend(self.select_leds());
+
+        list
+    }
+
+    /// Displays the track number in the track list if available,
+    /// otherwise the index of the player.
+    fn assignment_update(&mut self) -> Vec<Msg> {
+        use display_7_seg::*;
+        use std::io::Write;
+
+        let mut assignment = [b' '; 2];
+        if let Some(index) = self.track_list_index.or(self.player_index) {
+            let mut cur = std::io::Cursor::new(assignment.as_mut_slice());
+            write!(cur, "{:>2}", (index + 1) % 100).unwrap();
+        }
+
+        let mut list = Vec::new();
+        for (idx, (&last_digit, digit)) in self.last_assignment.iter().zip(assignment).enumerate() {
+            if last_digit != digit {
+                list.push([TAG.into(), ASSIGNMENT_LEFT_DIGIT - idx as u8, digit].into());
+            }
+        }
+
+        self.last_assignment = assignment;
+
+        list
+    }
+}
+
+/// Timecode display.
+impl Mackie {
+    fn device_next_time_mode(&mut self) -> Vec<Msg> {
+        self.time_mode = self.time_mode.next();
+        log::debug!("Time mode {:?}", self.time_mode);
+
+        let mut list = self.time_mode_leds();
+        list.extend(self.timecode_update());
+
+        list
+    }
+
+    fn time_mode_leds(&mut self) -> Vec<Msg> {
+        use button::*;
+
+        let (smpte, beats) = self.time_mode.leds();
+
+        vec![
+            self.led(SMPTE_LED, smpte.into()),
+            self.led(BEATS_LED, beats.into()),
+        ]
+    }
+
+    fn timecode_update(&mut self) -> Vec<Msg> {
+        use display_7_seg::*;
+        use TimeMode::*;
+
+        let tc = match self.time_mode {
+            Elapsed => TimecodeBreakDown::from(Timecode::from(self.pos)),
+            Remaining => match self.track_duration {
+                Some(duration) => {
+                    TimecodeBreakDown::from(Timecode::from(duration.saturating_sub(self.pos)))
+                        .with_minus_sign()
+                }
+                None => TimecodeBreakDown::default(),
+            },
+            TrackElapsed => TimecodeBreakDown::from(Timecode::from(self.pos))
+                .with_track_number(self.track_number),
+        };
+
+        let mut list = Vec::new();
+        for (idx, (&last_digit, digit)) in self.last_tc.0.iter().zip(tc.0).enumerate() {
+            if last_digit != digit {
+                list.push([TAG.into(), TIME_LEFT_DIGIT - idx as u8, digit].into());
+            }
+        }
+
+        self.last_tc = tc;
+
+        list
+    }
+}
+
+/// Device handshake.
+impl Mackie {
+    fn device_sysex(&mut self, msg: midi::Msg) -> Vec<Msg> {
+        self.device_connection(msg)
+            .unwrap_or_else(|err| Msg::from_connection_result(Err(err)).into())
+    }
+
+    fn device_connection(&mut self, msg: midi::Msg) -> Result<Vec<Msg>, Error> {
+        use crate::bytes::Displayable;
+        use connection::*;
+        use Error::*;
+
+        let payload = msg.parse_sysex()?;
+
+        // Check header
+        if payload.len() < 5 {
             return Err(UnexpectedDeviceMsg(msg.display().to_owned()));
         }
 
-        if payload[0..3] != MACKIE_ID {
+        if payload[0..3] != Self::MACKIE_ID {
             return Err(ManufacturerMismatch {
-                expected: Displayable::from(MACKIE_ID.as_slice()).to_owned(),
+                expected: Displayable::from(Self::MACKIE_ID.as_slice()).to_owned(),
                 found: Displayable::from(&payload[0..3]).to_owned(),
             });
         }
 
         if self.device_id != payload[3] {
-            let err = DeviceIdMismatch {
-                expected: self.device_id,
-                found: payload[3],
-            };
-            log::debug!("{err}");
-            return Err(err);
+            if self.learns_device_id && matches!(self.state, State::Connecting(_)) {
+                log::info!(
+                    "Using device id {:#02x} instead of {:#02x}",
+                    payload[3],
+                    self.device_id,
+                );
+                self.device_id = payload[3];
+            } else {
+                let err = DeviceIdMismatch {
+                    expected: self.device_id,
+                    found: payload[3],
+                };
+                log::debug!("{err}");
+                return Err(err);
+            }
         }
 
         let msg_list = match (payload[4], payload.get(5..)) {
+            (QUERY_HOST, Some(_))
+                if !matches!(self.state, State::Connecting(_) | State::Disconnected) =>
+            {
+                // Reply to a keepalive query.
+                Msg::none()
+            }
             (QUERY_HOST, Some(serial_challenge)) => self
                 .device_query_host(serial_challenge)
                 .map_err(|_| UnexpectedDeviceMsg(msg.display().to_owned()))?,
@@ -495,40 +1753,88 @@ impl Mackie {
                 log::error!("Device QUERY HOST: invalid serial / challenge");
             })?;
 
-        let msg_list =
-            if self.device_id == LOGIC_CONTROL_ID || self.device_id == LOGIC_CONTROL_EXT_ID {
-                let mut resp = [0u8; 5 + 7 + 4];
+        let msg_list = if self.is_logic_control() {
+            let mut resp = [0u8; 5 + 7 + 4];
 
-                self.prepare_payload(&mut resp, HOST_REPLY);
-                resp[5..12].copy_from_slice(ser);
-                resp[12] = 0x7F & (chlg[0] + (chlg[1] ^ 0x0a) - chlg[3]);
-                resp[13] = 0x7F & ((chlg[2] >> 4) ^ (chlg[0] + chlg[3]));
-                resp[14] = 0x7F & ((chlg[3] - (chlg[2] << 2)) ^ (chlg[0] | chlg[1]));
-                resp[15] = 0x7F & (chlg[1] - chlg[2] + (0xf0 ^ (chlg[3] << 4)));
+            self.prepare_payload(&mut resp, HOST_REPLY);
+            resp[5..12].copy_from_slice(ser);
+            resp[12..].copy_from_slice(&Self::challenge_response(chlg));
 
-                self.state = State::Connecting(ConnectionStatus::ChallengeReplied);
-                log::debug!("Device QUERY HOST challenge replied");
+            self.state = State::Connecting(ConnectionStatus::ChallengeReplied);
+            log::debug!("Device QUERY HOST challenge replied");
 
-                vec![
-                    midi::Msg::new_sysex(&resp).to_device(),
-                    Msg::connetion_in_progress(),
-                ]
-            } else {
-                // No need for a challenge reply
-                self.device_connected()
-            };
+            vec![
+                midi::Msg::new_sysex(&resp).to_device(),
+                Msg::connetion_in_progress(),
+            ]
+        } else {
+            // No need for a challenge reply
+            self.device_connected()
+        };
 
         Ok(msg_list)
     }
 
+    /// Computes the Logic Control response to the 4 bytes `chlg`.
+    ///
+    /// The computation relies on 8 bits wrapping arithmetic.
+    fn challenge_response(chlg: &[u8]) -> [u8; 4] {
+        [
+            chlg[0].wrapping_add(chlg[1] ^ 0x0a).wrapping_sub(chlg[3]),
+            (chlg[2] >> 4) ^ chlg[0].wrapping_add(chlg[3]),
+            chlg[3].wrapping_sub(chlg[2] << 2) ^ (chlg[0] | chlg[1]),
+            chlg[1]
+                .wrapping_sub(chlg[2])
+                .wrapping_add(0xf0 ^ (chlg[3] << 4)),
+        ]
+        .map(|byte| 0x7F & byte)
+    }
+
     fn device_connected(&mut self) -> Vec<Msg> {
         log::debug!("Connected to device {:#02x}", self.device_id);
         self.state = State::PendingAppData;
+        // Don't know what the device currently displays.
+        self.last_lcd = LcdBuffer::invalidated();
 
-        vec![
+        let mut list = vec![
             Msg::from_connection_result(Ok(())),
             CtrlSurfEvent::DataRequest.to_app(),
-        ]
+        ];
+        list.extend(self.time_mode_leds());
+        list.extend(self.leds_restore());
+        list.extend((0..self.vpots.len()).map(|idx| self.vpot_ring_msg(idx)));
+
+        let mut payload = [0u8; 7];
+        self.prepare_payload(&mut payload, meter::MODE);
+        payload[5] = 0; // strip
+        payload[6] = meter::SIGNAL_LED;
+        list.push(midi::Msg::new_sysex(&payload).to_device());
+
+        list
+    }
+
+    /// Builds the LCD message for the characters which changed since last update.
+    fn lcd_update(&mut self) -> Vec<Msg> {
+        let (first, last) = match self.lcd.changed_range(&self.last_lcd) {
+            Some(range) => range,
+            None => return Msg::none(),
+        };
+
+        let mut payload = Vec::with_capacity(5 + 1 + last + 1 - first);
+        payload.extend(self.payload_for(lcd::WRITE));
+        payload.push(first as u8);
+        payload.extend(&self.lcd.0[first..=last]);
+
+        self.last_lcd = self.lcd;
+
+        midi::Msg::new_sysex(&payload).to_device().into()
+    }
+
+    fn is_logic_control(&self) -> bool {
+        matches!(
+            self.device_id,
+            Self::LOGIC_CONTROL_ID | Self::LOGIC_CONTROL_EXT_ID
+        )
     }
 
     fn payload_for(&self, req_id: u8) -> [u8; 5] {
@@ -539,12 +1845,22 @@ impl Mackie {
     }
 
     fn prepare_payload(&self, payload: &mut [u8], req_id: u8) {
-        payload[..=2].copy_from_slice(&connection::MACKIE_ID);
+        payload[..=2].copy_from_slice(&Self::MACKIE_ID);
         payload[3] = self.device_id;
         payload[4] = req_id;
     }
 }
 
+/// Decodes a relative encoder value into signed ticks.
+fn relative_ticks(value: u8) -> i16 {
+    let ticks = (value & encoder::TICKS_MASK) as i16;
+    if value & encoder::CCW == 0 {
+        ticks
+    } else {
+        -ticks
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 struct TimecodeBreakDown([u8; 10]);
 
@@ -554,6 +1870,28 @@ impl Default for TimecodeBreakDown {
     }
 }
 
+impl TimecodeBreakDown {
+    fn with_minus_sign(mut self) -> Self {
+        self.0[0] = b'-';
+        self
+    }
+
+    /// Displays the track number in place of the hours.
+    fn with_track_number(mut self, number: Option<u32>) -> Self {
+        use std::io::Write;
+
+        match number {
+            Some(number) => {
+                let mut cur = std::io::Cursor::new(&mut self.0[..3]);
+                write!(cur, "{:>3}", number % 1_000).unwrap();
+            }
+            None => self.0[..3].copy_from_slice(b"  -"),
+        }
+
+        self
+    }
+}
+
 impl From<ctrl_surf::Timecode> for TimecodeBreakDown {
     fn from(tc: ctrl_surf::Timecode) -> Self {
         use std::io::Write;
@@ -572,3 +1910,73 @@ impl From<ctrl_surf::Timecode> for TimecodeBreakDown {
         this
     }
 }
+
+#[derive(Clone, Copy, Debug)]
+struct LcdBuffer([u8; lcd::LEN]);
+
+impl Default for LcdBuffer {
+    fn default() -> Self {
+        Self([b' '; lcd::LEN])
+    }
+}
+
+impl LcdBuffer {
+    /// Returns a buffer which differs from any displayable content.
+    fn invalidated() -> Self {
+        Self([0; lcd::LEN])
+    }
+
+    /// Returns the first and last indices of the characters which differ.
+    fn changed_range(&self, other: &Self) -> Option<(usize, usize)> {
+        let is_changed = |(cur, other): (&u8, &u8)| cur != other;
+
+        let first = self.0.iter().zip(other.0.iter()).position(is_changed)?;
+        let last = self.0.iter().zip(other.0.iter()).rposition(is_changed)?;
+
+        Some((first, last))
+    }
+
+    fn set_row(&mut self, row: usize, text: &str) {
+        let start = row * lcd::ROW_LEN;
+        self.set_text(start..start + lcd::ROW_LEN, text);
+    }
+
+    /// Sets the text for the strip at `index` in `row`.
+    ///
+    /// The last character of the strip is kept blank to separate strips.
+    fn set_strip(&mut self, row: usize, index: usize, text: &str) {
+        let start = row * lcd::ROW_LEN + index * lcd::STRIP_LEN;
+        self.set_text(start..start + lcd::STRIP_LEN - 1, text);
+        self.0[start + lcd::STRIP_LEN - 1] = b' ';
+    }
+
+    fn set_text(&mut self, range: std::ops::Range<usize>, text: &str) {
+        let mut chars = text.chars();
+        for byte in self.0[range].iter_mut() {
+            *byte = match chars.next() {
+                Some(c) if c == ' ' || c.is_ascii_graphic() => c as u8,
+                Some(_) => b'?',
+                None => b' ',
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn challenge_response() {
+        for (chlg, resp) in [
+            ([0x10, 0x20, 0x08, 0x01], [0x39, 0x11, 0x51, 0x78]),
+            // Underflows.
+            ([0x01, 0x02, 0x03, 0x04], [0x05, 0x05, 0x7b, 0x2f]),
+            // Overflows.
+            ([0x00, 0x7f, 0x00, 0x00], [0x75, 0x00, 0x7f, 0x6f]),
+            ([0x7f, 0x7f, 0x7f, 0x7f], [0x75, 0x79, 0x7c, 0x00]),
+        ] {
+            assert_eq!(Mackie::challenge_response(&chlg), resp, "{chlg:02x?}");
+        }
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    ctrl_surf::{
+        event::{self, *},
+        ControlSurface, Msg,
+    },
+    midi::{self, smpte, smpte::FrameRate},
+};
+
+mod header {
+    pub const UNIVERSAL_REALTIME: u8 = 0x7f;
+    pub const ALL_CALL: u8 = 0x7f;
+    pub const MMC_COMMAND: u8 = 0x06;
+}
+
+mod command {
+    pub const STOP: u8 = 0x01;
+    pub const PLAY: u8 = 0x02;
+    pub const DEFERRED_PLAY: u8 = 0x03;
+    pub const FAST_FORWARD: u8 = 0x04;
+    pub const REWIND: u8 = 0x05;
+    pub const PAUSE: u8 = 0x09;
+    pub const LOCATE: u8 = 0x44;
+
+    /// Byte count for LOCATE TARGET.
+    pub const LOCATE_COUNT: u8 = 0x06;
+    pub const LOCATE_TARGET: u8 = 0x01;
+}
+
+/// Jump between the expected & the received positions considered as a seek.
+const POSITION_JUMP_THRSD: Duration = Duration::from_secs(1);
+
+/// MIDI Machine Control.
+///
+/// MMC commands from the device control the current player. In the
+/// other direction, the player state is sent as MMC commands, so that
+/// MMC slaved hardware follows the player. Seeks & track changes are
+/// sent as LOCATE commands, using the configured frame rate.
+///
+/// The players can't shuttle, so FAST FORWARD & REWIND only step
+/// once in the corresponding direction.
+///
+/// Plain MMC devices don't perform any handshake,
+/// so the device is considered connected right away.
+#[derive(Debug)]
+pub struct Mmc {
+    is_connected: bool,
+    frame_rate: FrameRate,
+    /// Last transport command, sent or received.
+    command: Option<u8>,
+    position: Duration,
+    /// When `position` was received.
+    position_instant: Option<Instant>,
+    /// Last position located by the device.
+    located: Option<Duration>,
+    track_id: Option<Arc<str>>,
+}
+
+impl Default for Mmc {
+    fn default() -> Self {
+        Self {
+            is_connected: false,
+            frame_rate: FrameRate::default(),
+            command: None,
+            position: Duration::ZERO,
+            position_instant: None,
+            located: None,
+            track_id: None,
+        }
+    }
+}
+
+impl ControlSurface for Mmc {
+    fn start_connection(&mut self) -> Vec<Msg> {
+        log::debug!("Connecting to MMC device");
+
+        self.is_connected = true;
+
+        vec![
+            Msg::from_connection_result(Ok(())),
+            CtrlSurfEvent::DataRequest.to_app(),
+        ]
+    }
+
+    fn abort_connection(&mut self) -> Vec<Msg> {
+        Msg::none()
+    }
+
+    fn msg_from_device(&mut self, msg: midi::Msg) -> Vec<Msg> {
+        use command::*;
+        use header::*;
+        use Transport::*;
+
+        let payload = match msg.parse_sysex() {
+            Ok(payload) => payload,
+            Err(_) => return Msg::none(),
+        };
+
+        let (cmd, args) = match payload {
+            [UNIVERSAL_REALTIME, _device, MMC_COMMAND, cmd, args @ ..] => (*cmd, args),
+            _ => return Msg::none(),
+        };
+
+        match cmd {
+            STOP => {
+                self.command = Some(STOP);
+                return Stop.to_app().into();
+            }
+            PLAY | DEFERRED_PLAY => {
+                self.command = Some(PLAY);
+                return Play.to_app().into();
+            }
+            PAUSE => {
+                self.command = Some(PAUSE);
+                return Pause.to_app().into();
+            }
+            FAST_FORWARD => return StepForward(1).to_app().into(),
+            REWIND => return StepBackward(1).to_app().into(),
+            LOCATE => match args {
+                [LOCATE_COUNT, LOCATE_TARGET, hr, mn, sc, fr, _ff] => {
+                    let time = smpte::Time::from_bytes(*hr, *mn, *sc, *fr);
+                    log::debug!("MMC: locate {time}");
+
+                    let pos = time.to_duration();
+                    self.located = Some(pos);
+                    return SetPosition(pos).to_app().into();
+                }
+                _ => log::debug!("MMC: unsupported locate {}", msg.display()),
+            },
+            _ => log::debug!("MMC: unsupported command {}", msg.display()),
+        }
+
+        Msg::none()
+    }
+
+    fn event_from_app(&mut self, event: AppEvent) -> Vec<Msg> {
+        use command::*;
+
+        if !self.is_connected {
+            log::debug!("Ignoring App event: Control surface not connected.");
+            return Msg::none();
+        }
+
+        use AppEvent::*;
+        match event {
+            Transport(event) => {
+                use event::Transport::*;
+                match event {
+                    Play => return self.command_msg(PLAY),
+                    Pause => return self.command_msg(PAUSE),
+                    Stop => return self.reset(),
+                    SetPosition(pos) => return self.locate(pos),
+                    _ => (),
+                }
+            }
+            NewApp(app) => {
+                log::debug!("New application {app}");
+                return CtrlSurfEvent::DataRequest.to_app().into();
+            }
+            Data(data) => {
+                use event::Data::*;
+                match data {
+                    PlaybackStatus(status) => {
+                        use crate::ctrl_surf::data::PlaybackStatus::*;
+
+                        match status {
+                            Playing => return self.command_msg(PLAY),
+                            Paused => return self.command_msg(PAUSE),
+                            Stopped => return self.command_msg(STOP),
+                        }
+                    }
+                    Track(track) => return self.app_track(track.id),
+                    Position(pos) => return self.app_position(pos),
+                    _ => (),
+                }
+            }
+            Mixer(_) | Strip(..) => (),
+        }
+
+        Msg::none()
+    }
+
+    fn set_frame_rate(&mut self, rate: FrameRate) -> Vec<Msg> {
+        self.frame_rate = rate;
+        Msg::none()
+    }
+
+    fn has_handshake(&self) -> bool {
+        false
+    }
+
+    fn is_connected(&self) -> bool {
+        self.is_connected
+    }
+
+    fn reset(&mut self) -> Vec<Msg> {
+        let list = self.command_msg(command::STOP);
+
+        self.position = Duration::ZERO;
+        self.position_instant = None;
+        self.located = None;
+        self.track_id = None;
+
+        list
+    }
+
+    fn disconnect(&mut self) -> Vec<Msg> {
+        if !self.is_connected {
+            return Msg::none();
+        }
+
+        log::debug!("Disconnecting from MMC device");
+
+        let list = self.reset();
+        self.command = None;
+        self.is_connected = false;
+
+        list
+    }
+}
+
+/// App events.
+impl Mmc {
+    /// Locates the start of the track if it's a new track.
+    fn app_track(&mut self, track_id: Option<Arc<str>>) -> Vec<Msg> {
+        if track_id == self.track_id {
+            // Same track sent again, e.g. after a data request.
+            return Msg::none();
+        }
+        self.track_id = track_id;
+
+        self.locate(Duration::ZERO)
+    }
+
+    /// Sends a LOCATE if the position doesn't follow the playback.
+    fn app_position(&mut self, pos: Duration) -> Vec<Msg> {
+        let expected = match self.position_instant {
+            Some(instant) if self.command == Some(command::PLAY) => {
+                self.position + instant.elapsed()
+            }
+            _ => self.position,
+        };
+
+        self.position = pos;
+        self.position_instant = Some(Instant::now());
+
+        if pos.abs_diff(expected) < POSITION_JUMP_THRSD {
+            return Msg::none();
+        }
+
+        // Don't send back the position the device just located.
+        if matches!(self.located.take(), Some(located) if pos.abs_diff(located) < POSITION_JUMP_THRSD)
+        {
+            return Msg::none();
+        }
+
+        self.locate(pos)
+    }
+}
+
+/// Messages to device.
+impl Mmc {
+    /// Sends `cmd` unless it is the last known transport command.
+    fn command_msg(&mut self, cmd: u8) -> Vec<Msg> {
+        use header::*;
+
+        if self.command == Some(cmd) {
+            return Msg::none();
+        }
+        self.command = Some(cmd);
+
+        midi::Msg::new_sysex(&[UNIVERSAL_REALTIME, ALL_CALL, MMC_COMMAND, cmd])
+            .to_device()
+            .into()
+    }
+
+    fn locate(&mut self, pos: Duration) -> Vec<Msg> {
+        use command::*;
+        use header::*;
+
+        self.position = pos;
+        self.position_instant = Some(Instant::now());
+
+        let time = smpte::Time::from_duration(pos, self.frame_rate);
+        log::debug!("MMC: sending locate {time}");
+
+        midi::Msg::new_sysex(&[
+            UNIVERSAL_REALTIME,
+            ALL_CALL,
+            MMC_COMMAND,
+            LOCATE,
+            LOCATE_COUNT,
+            LOCATE_TARGET,
+            time.hours_byte(),
+            time.m,
+            time.s,
+            time.f,
+            0, // sub-frames
+        ])
+        .to_device()
+        .into()
+    }
+}
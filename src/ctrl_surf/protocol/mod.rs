@@ -1,2 +1,29 @@
+mod composite;
+pub use composite::Composite;
+
+mod generic;
+pub use generic::Generic;
+
+mod hui;
+pub use hui::Hui;
+
+mod layered;
+pub use layered::{Layer, Layered};
+
 mod mackie;
 pub use mackie::Mackie;
+
+mod mmc;
+pub use mmc::Mmc;
+
+mod mtc;
+pub use mtc::Mtc;
+
+mod nano_kontrol2;
+pub use nano_kontrol2::NanoKontrol2;
+
+mod osc;
+pub use osc::Osc;
+
+mod xtouch_mini;
+pub use xtouch_mini::XTouchMini;
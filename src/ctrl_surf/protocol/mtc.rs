@@ -0,0 +1,360 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    ctrl_surf::{
+        event::{self, *},
+        ControlSurface, Msg,
+    },
+    midi::{self, smpte::FrameRate, smpte::Time},
+};
+
+mod full_frame {
+    pub const UNIVERSAL_REALTIME: u8 = 0x7f;
+    pub const ALL_CALL: u8 = 0x7f;
+    pub const MTC: u8 = 0x01;
+    pub const FULL_MESSAGE: u8 = 0x01;
+}
+
+mod quarter_frame {
+    use crate::midi::Tag;
+    pub const TAG: Tag = Tag::from(0xf1);
+
+    /// Number of pieces, spanning 2 frames.
+    pub const PIECES: u8 = 8;
+    pub const FRAMES: u64 = 2;
+    pub const PER_FRAME: f64 = 4.0;
+}
+
+/// Jump between the extrapolated & the received positions considered as a seek.
+const POSITION_JUMP_THRSD: Duration = Duration::from_secs(1);
+/// Drift between the extrapolated & the received positions before re-anchoring the clock.
+const POSITION_DRIFT_TOLERANCE: Duration = Duration::from_millis(50);
+/// Max quarter frames to catch up with before resynchronizing with a full frame.
+const MAX_LATE_QUARTER_FRAMES: u8 = quarter_frame::PIECES;
+
+/// MIDI Timecode generator following the current player.
+///
+/// Quarter frames are sent while the player is playing. Since positions
+/// are only received periodically, the position is extrapolated using
+/// the local clock between two updates. Full frames are sent on seeks,
+/// track changes & `SetPosition`.
+///
+/// The device is considered connected right away.
+#[derive(Debug)]
+pub struct Mtc {
+    is_connected: bool,
+    frame_rate: FrameRate,
+    is_playing: bool,
+    /// Last known position.
+    position: Duration,
+    /// When `position` was known, while playing.
+    position_instant: Option<Instant>,
+    /// Frame at the start of the current quarter frame sequence.
+    qf_frame: u64,
+    next_qf_piece: u8,
+    track_id: Option<Arc<str>>,
+}
+
+impl Default for Mtc {
+    fn default() -> Self {
+        Self {
+            is_connected: false,
+            frame_rate: FrameRate::default(),
+            is_playing: false,
+            position: Duration::ZERO,
+            position_instant: None,
+            qf_frame: 0,
+            next_qf_piece: 0,
+            track_id: None,
+        }
+    }
+}
+
+impl ControlSurface for Mtc {
+    fn start_connection(&mut self) -> Vec<Msg> {
+        log::debug!("Starting MTC output");
+
+        self.is_connected = true;
+
+        vec![
+            Msg::from_connection_result(Ok(())),
+            CtrlSurfEvent::DataRequest.to_app(),
+        ]
+    }
+
+    fn abort_connection(&mut self) -> Vec<Msg> {
+        Msg::none()
+    }
+
+    fn msg_from_device(&mut self, _msg: midi::Msg) -> Vec<Msg> {
+        Msg::none()
+    }
+
+    fn event_from_app(&mut self, event: AppEvent) -> Vec<Msg> {
+        if !self.is_connected {
+            log::debug!("Ignoring App event: Control surface not connected.");
+            return Msg::none();
+        }
+
+        use AppEvent::*;
+        match event {
+            Transport(event) => {
+                use event::Transport::*;
+                match event {
+                    Play => return self.app_play(true),
+                    Pause => return self.app_play(false),
+                    Stop => return self.reset(),
+                    SetPosition(pos) => return self.locate(pos),
+                    _ => (),
+                }
+            }
+            NewApp(app) => {
+                log::debug!("New application {app}");
+                return CtrlSurfEvent::DataRequest.to_app().into();
+            }
+            Data(data) => {
+                use event::Data::*;
+                match data {
+                    PlaybackStatus(status) => return self.app_play(status.is_playing()),
+                    Track(track) => return self.app_track(track.id),
+                    Position(pos) => return self.app_position(pos),
+                    _ => (),
+                }
+            }
+            Mixer(_) | Strip(..) => (),
+        }
+
+        Msg::none()
+    }
+
+    fn set_frame_rate(&mut self, rate: FrameRate) -> Vec<Msg> {
+        self.frame_rate = rate;
+
+        if !self.is_connected {
+            return Msg::none();
+        }
+
+        self.locate(self.cur_position())
+    }
+
+    fn has_handshake(&self) -> bool {
+        false
+    }
+
+    fn is_connected(&self) -> bool {
+        self.is_connected
+    }
+
+    fn reset(&mut self) -> Vec<Msg> {
+        self.is_playing = false;
+        self.track_id = None;
+
+        self.locate(Duration::ZERO)
+    }
+
+    fn disconnect(&mut self) -> Vec<Msg> {
+        if !self.is_connected {
+            return Msg::none();
+        }
+
+        log::debug!("Stopping MTC output");
+
+        let list = self.reset();
+        self.is_connected = false;
+
+        list
+    }
+
+    fn tick(&mut self) -> Vec<Msg> {
+        if !self.is_connected || !self.is_playing {
+            return Msg::none();
+        }
+
+        let cur_pos = self.cur_position();
+        let mut list = Vec::new();
+        while self.next_qf_pos() <= cur_pos {
+            if list.len() >= MAX_LATE_QUARTER_FRAMES as usize {
+                log::debug!("MTC: late quarter frames, resynchronizing");
+                return self.locate(cur_pos);
+            }
+
+            list.push(self.next_quarter_frame());
+        }
+
+        list.push(Msg::schedule_tick(self.next_qf_pos() - cur_pos));
+
+        list
+    }
+}
+
+/// App events.
+impl Mtc {
+    fn app_play(&mut self, is_playing: bool) -> Vec<Msg> {
+        if self.is_playing == is_playing {
+            return Msg::none();
+        }
+
+        let pos = self.cur_position();
+        self.is_playing = is_playing;
+        log::debug!("MTC: {}", if is_playing { "running" } else { "stopped" });
+
+        self.locate(pos)
+    }
+
+    /// Locates the start of the track if it's a new track.
+    fn app_track(&mut self, track_id: Option<Arc<str>>) -> Vec<Msg> {
+        if track_id == self.track_id {
+            // Same track sent again, e.g. after a data request.
+            return Msg::none();
+        }
+        self.track_id = track_id;
+
+        self.locate(Duration::ZERO)
+    }
+
+    fn app_position(&mut self, pos: Duration) -> Vec<Msg> {
+        let drift = pos.abs_diff(self.cur_position());
+        if drift >= POSITION_JUMP_THRSD {
+            return self.locate(pos);
+        }
+
+        if drift >= POSITION_DRIFT_TOLERANCE || self.position_instant.is_none() {
+            self.anchor(pos);
+        }
+
+        Msg::none()
+    }
+}
+
+/// Clock.
+impl Mtc {
+    /// Returns the position extrapolated from the last known position.
+    fn cur_position(&self) -> Duration {
+        match self.position_instant {
+            Some(instant) if self.is_playing => self.position + instant.elapsed(),
+            _ => self.position,
+        }
+    }
+
+    fn anchor(&mut self, pos: Duration) {
+        self.position = pos;
+        self.position_instant = self.is_playing.then(Instant::now);
+    }
+
+    fn next_qf_pos(&self) -> Duration {
+        let frame = self.qf_frame as f64 + self.next_qf_piece as f64 / quarter_frame::PER_FRAME;
+        Duration::from_secs_f64(frame / self.frame_rate.fps())
+    }
+}
+
+/// Messages to device.
+impl Mtc {
+    /// Sends a full frame for `pos` & restarts the quarter frames from there if playing.
+    fn locate(&mut self, pos: Duration) -> Vec<Msg> {
+        use full_frame::*;
+
+        self.anchor(pos);
+
+        let time = Time::from_duration(pos, self.frame_rate);
+        log::debug!("MTC: full frame {time}");
+
+        let mut list = vec![midi::Msg::new_sysex(&[
+            UNIVERSAL_REALTIME,
+            ALL_CALL,
+            MTC,
+            FULL_MESSAGE,
+            time.hours_byte(),
+            time.m,
+            time.s,
+            time.f,
+        ])
+        .to_device()];
+
+        if self.is_playing {
+            // Quarter frame sequences start on even frames.
+            let frame = (pos.as_secs_f64() * self.frame_rate.fps()).ceil() as u64;
+            self.qf_frame = frame + frame % 2;
+            self.next_qf_piece = 0;
+            list.push(Msg::schedule_tick(self.next_qf_pos().saturating_sub(pos)));
+        }
+
+        list
+    }
+
+    fn next_quarter_frame(&mut self) -> Msg {
+        let piece = self.next_qf_piece;
+        let time = Time::from_frame_count(self.qf_frame, self.frame_rate);
+        let value = match piece {
+            0 => time.f & 0x0f,
+            1 => time.f >> 4,
+            2 => time.s & 0x0f,
+            3 => time.s >> 4,
+            4 => time.m & 0x0f,
+            5 => time.m >> 4,
+            6 => time.h & 0x0f,
+            _ => time.hours_byte() >> 4,
+        };
+
+        self.next_qf_piece = (piece + 1) % quarter_frame::PIECES;
+        if self.next_qf_piece == 0 {
+            self.qf_frame += quarter_frame::FRAMES;
+        }
+
+        let tag: u8 = quarter_frame::TAG.into();
+        [tag, (piece << 4) | value].into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quarter_frames(mtc: &mut Mtc) -> Vec<[u8; 2]> {
+        (0..quarter_frame::PIECES)
+            .map(|_| match mtc.next_quarter_frame() {
+                Msg::ToDevice(msg) => msg.inner().try_into().unwrap(),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn quarter_frame_pieces() {
+        let mut mtc = Mtc {
+            frame_rate: FrameRate::Fps25,
+            ..Default::default()
+        };
+
+        // 23:35:42:14
+        mtc.qf_frame = ((23 * 60 + 35) * 60 + 42) * 25 + 14;
+        let pieces = quarter_frames(&mut mtc);
+        assert_eq!(
+            pieces,
+            [
+                [0xf1, 0x0e],
+                [0xf1, 0x10],
+                [0xf1, 0x2a],
+                [0xf1, 0x32],
+                [0xf1, 0x43],
+                [0xf1, 0x52],
+                [0xf1, 0x67],
+                // Rate code 1 in bits 1 & 2, hours bit 4 in bit 0.
+                [0xf1, 0x73],
+            ]
+        );
+        assert_eq!(mtc.qf_frame, ((23 * 60 + 35) * 60 + 42) * 25 + 16);
+        assert_eq!(mtc.next_qf_piece, 0);
+
+        // 01:10:00;00
+        mtc.frame_rate = FrameRate::Fps29_97Df;
+        mtc.qf_frame = 7 * 17_982;
+        let pieces = quarter_frames(&mut mtc);
+        assert_eq!(pieces[4], [0xf1, 0x4a]);
+        assert_eq!(pieces[5], [0xf1, 0x50]);
+        assert_eq!(pieces[6], [0xf1, 0x61]);
+        assert_eq!(pieces[7], [0xf1, 0x74]);
+    }
+}
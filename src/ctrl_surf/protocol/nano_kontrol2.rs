@@ -0,0 +1,653 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    ctrl_surf::{
+        event::{self, *},
+        ControlSurface, Error, FaderCurve, LoopStatus, Markers, Msg,
+    },
+    midi,
+};
+
+mod connection {
+    pub const KORG_ID: u8 = 0x42;
+    pub const NANO_KONTROL2_FAMILY: [u8; 2] = [0x13, 0x01];
+
+    /// Header for Korg exclusive messages, following the Korg id & global channel.
+    pub const EXCLUSIVE_HEADER: [u8; 4] = [0x00, 0x01, 0x13, 0x00];
+    /// Or'ed with the global channel.
+    pub const EXCLUSIVE_CHANNEL: u8 = 0x40;
+
+    pub const SCENE_DUMP_REQUEST: [u8; 3] = [0x1f, 0x10, 0x00];
+    pub const SCENE_DUMP: [u8; 6] = [0x7f, 0x7f, 0x02, 0x03, 0x05, 0x40];
+    pub const DATA_LOAD_COMPLETED: [u8; 3] = [0x5f, 0x23, 0x00];
+    pub const DATA_LOAD_ERROR: [u8; 3] = [0x5f, 0x24, 0x00];
+
+    /// Offset of the LED mode in the decoded scene data.
+    pub const LED_MODE_OFFSET: usize = 2;
+    pub const LED_MODE_INTERNAL: u8 = 0;
+    pub const LED_MODE_EXTERNAL: u8 = 1;
+}
+
+/// Controls & buttons in the factory scene.
+///
+/// In external LED mode, the LED of a button is driven
+/// by sending the control change for the button.
+mod control {
+    use crate::midi::Tag;
+    pub const TAG: Tag = Tag::from(0xb0);
+
+    pub const PRESSED: u8 = 127;
+    pub const ON: u8 = 127;
+    pub const OFF: u8 = 0;
+    pub const MAX: u8 = 127;
+
+    pub const FADER_FIRST: u8 = 0;
+    pub const FADER_LAST: u8 = 7;
+    pub const KNOB_FIRST: u8 = 16;
+    pub const KNOB_LAST: u8 = 23;
+    pub const SOLO_FIRST: u8 = 32;
+    pub const SOLO_LAST: u8 = 39;
+    pub const MUTE_FIRST: u8 = 48;
+    pub const MUTE_LAST: u8 = 55;
+    pub const REC_FIRST: u8 = 64;
+
+    pub const PLAY: u8 = 41;
+    pub const STOP: u8 = 42;
+    pub const REWIND: u8 = 43;
+    pub const FAST_FWD: u8 = 44;
+    pub const REC: u8 = 45;
+    pub const CYCLE: u8 = 46;
+    pub const TRACK_PREVIOUS: u8 = 58;
+    pub const TRACK_NEXT: u8 = 59;
+    pub const MARKER_SET: u8 = 60;
+    pub const MARKER_PREVIOUS: u8 = 61;
+    pub const MARKER_NEXT: u8 = 62;
+}
+
+const STRIPS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Connecting(ConnectionStatus),
+    Connected,
+    Disconnected,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ConnectionStatus {
+    DeviceQueried,
+    SceneRequested,
+    SceneSent,
+}
+
+/// A fader / knob strip bound to a player.
+#[derive(Clone, Debug, Default)]
+struct Strip {
+    name: Option<Arc<str>>,
+    is_muted: bool,
+}
+
+/// Korg nanoKONTROL2 using the factory scene.
+///
+/// The LEDs are switched to external mode during the connection, so that
+/// they reflect the app state. The scene is restored on disconnection.
+///
+/// - The transport buttons control the current player. TRACK ◀ / ▶ select
+///   the previous / next track, REW / FF step backward / forward,
+///   MARKER SET sets the A-B loop markers and MARKER ◀ / ▶ seek to them.
+/// - CYCLE cycles through the loop statuses & REC toggles shuffle.
+/// - Each strip is bound to a player from the players list: the fader controls
+///   the volume, the knob the balance, M toggles mute & S selects the player.
+#[derive(Debug)]
+pub struct NanoKontrol2 {
+    state: State,
+    /// Whether a keepalive identity request is waiting for a device message.
+    is_keepalive_pending: bool,
+    global_chan: u8,
+    chan: midi::Channel,
+    /// Scene with the LED mode as found on the device, if it was changed.
+    orig_scene: Option<Vec<u8>>,
+    is_playing: bool,
+    shuffle: bool,
+    loop_status: LoopStatus,
+    markers: Markers,
+    fader_curve: FaderCurve,
+    player_index: Option<usize>,
+    /// Whether the players list is not empty.
+    has_players: bool,
+    strips: [Strip; STRIPS],
+}
+
+impl Default for NanoKontrol2 {
+    fn default() -> Self {
+        Self {
+            state: State::Disconnected,
+            is_keepalive_pending: false,
+            global_chan: 0,
+            chan: midi::Channel::default(),
+            orig_scene: None,
+            is_playing: false,
+            shuffle: false,
+            loop_status: LoopStatus::None,
+            markers: Markers::default(),
+            fader_curve: FaderCurve::default(),
+            player_index: None,
+            has_players: false,
+            strips: Default::default(),
+        }
+    }
+}
+
+impl ControlSurface for NanoKontrol2 {
+    fn start_connection(&mut self) -> Vec<Msg> {
+        log::debug!("Attempt to connect to nanoKONTROL2");
+
+        self.state = State::Connecting(ConnectionStatus::DeviceQueried);
+        self.is_keepalive_pending = false;
+
+        vec![
+            midi::sysex::identity_request().to_device(),
+            Msg::connetion_in_progress(),
+        ]
+    }
+
+    fn abort_connection(&mut self) -> Vec<Msg> {
+        if let State::Connecting(_) = self.state {
+            log::debug!("Aborting connection to nanoKONTROL2");
+
+            self.state = State::Disconnected;
+        }
+
+        Msg::none()
+    }
+
+    fn msg_from_device(&mut self, msg: midi::Msg) -> Vec<Msg> {
+        // Any message proves that the device is still there.
+        self.is_keepalive_pending = false;
+
+        let buf = msg.inner();
+        let &tag_chan = match buf.first() {
+            Some(tag_chan) => tag_chan,
+            None => return Msg::none(),
+        };
+
+        match midi::Tag::from_tag_chan(tag_chan) {
+            control::TAG if self.is_connected() => {
+                if let Some(&[id, value]) = buf.get(1..=2) {
+                    self.chan = midi::Channel::from(tag_chan);
+                    return self.device_control(id, value);
+                }
+            }
+            midi::sysex::TAG => {
+                return self
+                    .device_sysex(&msg)
+                    .unwrap_or_else(|err| Msg::from_connection_result(Err(err)).into());
+            }
+            _ => (),
+        }
+
+        Msg::none()
+    }
+
+    fn event_from_app(&mut self, event: AppEvent) -> Vec<Msg> {
+        if !self.is_connected() {
+            log::debug!("Ignoring App event: Control surface not connected.");
+            return Msg::none();
+        }
+
+        use AppEvent::*;
+        match event {
+            Transport(event) => {
+                use event::Transport::*;
+                match event {
+                    Play => return self.app_play(true),
+                    Pause => return self.app_play(false),
+                    Stop => return self.reset(),
+                    _ => (),
+                }
+            }
+            Mixer(mixer) => {
+                if let Some(index) = self.player_index {
+                    return self.app_strip(index, mixer);
+                }
+            }
+            Strip(index, mixer) => return self.app_strip(index, mixer),
+            NewApp(app) => {
+                log::debug!("New application {app}");
+                return CtrlSurfEvent::DataRequest.to_app().into();
+            }
+            Data(data) => {
+                use event::Data::*;
+                match data {
+                    PlaybackStatus(status) => {
+                        use crate::ctrl_surf::data::PlaybackStatus::*;
+
+                        match status {
+                            Playing => return self.app_play(true),
+                            Paused => return self.app_play(false),
+                            Stopped => return self.reset(),
+                        }
+                    }
+                    Shuffle(shuffle) => {
+                        self.shuffle = shuffle;
+                        return self.led(control::REC, shuffle).into();
+                    }
+                    LoopStatus(status) => {
+                        self.loop_status = status;
+                        let is_looping = status != crate::ctrl_surf::LoopStatus::None;
+                        return self.led(control::CYCLE, is_looping).into();
+                    }
+                    Markers(markers) => self.markers = markers,
+                    PlayerIndex(index) => return self.app_player_index(index),
+                    PlayerList(list) => return self.app_player_list(&list),
+                    _ => (),
+                }
+            }
+        }
+
+        Msg::none()
+    }
+
+    fn keepalive(&mut self) -> Vec<Msg> {
+        if !self.is_connected() {
+            return Msg::none();
+        }
+
+        if self.is_keepalive_pending {
+            log::warn!("nanoKONTROL2 stopped answering");
+            self.is_keepalive_pending = false;
+            self.state = State::Disconnected;
+
+            return Msg::connection_lost().into();
+        }
+
+        self.is_keepalive_pending = true;
+        midi::sysex::identity_request().to_device().into()
+    }
+
+    /// The faders aren't motorized, so only the next moves use the new curve.
+    fn set_fader_curve(&mut self, curve: FaderCurve) -> Vec<Msg> {
+        self.fader_curve = curve;
+        Msg::none()
+    }
+
+    fn is_connected(&self) -> bool {
+        self.state == State::Connected
+    }
+
+    fn reset(&mut self) -> Vec<Msg> {
+        use control::*;
+
+        let mut list = vec![
+            self.led(PLAY, false),
+            // STOP is lit while no player is available.
+            self.led(STOP, !self.has_players),
+            self.led(REC, false),
+            self.led(CYCLE, false),
+        ];
+
+        for index in 0..STRIPS as u8 {
+            list.push(self.led(SOLO_FIRST + index, false));
+            list.push(self.led(REC_FIRST + index, false));
+        }
+
+        // The strips stay bound to their players.
+        self.is_playing = false;
+        self.shuffle = false;
+        self.loop_status = LoopStatus::None;
+        self.markers = Markers::default();
+        self.player_index = None;
+
+        list
+    }
+
+    fn disconnect(&mut self) -> Vec<Msg> {
+        if !self.is_connected() {
+            return Msg::none();
+        }
+
+        log::debug!("Disconnecting from nanoKONTROL2");
+
+        let mut list = self.reset();
+        list.extend(self.release_strips());
+        list.push(self.led(control::STOP, false));
+
+        if let Some(scene) = self.orig_scene.take() {
+            // Back to the original LED mode.
+            list.push(self.scene_dump(&scene));
+        }
+
+        self.state = State::Disconnected;
+
+        list
+    }
+}
+
+/// Device events.
+impl NanoKontrol2 {
+    fn device_sysex(&mut self, msg: &midi::Msg) -> Result<Vec<Msg>, Error> {
+        use connection::*;
+
+        let payload = msg.parse_sysex()?;
+        if let Some(identity) = midi::sysex::parse_identity_reply(payload) {
+            return self.device_identity(identity);
+        }
+
+        match payload {
+            [KORG_ID, _, rem @ ..] if rem.starts_with(&EXCLUSIVE_HEADER) => {
+                self.device_exclusive(&rem[EXCLUSIVE_HEADER.len()..])
+            }
+            _ => Err(Error::UnexpectedDeviceMsg(msg.display().to_owned())),
+        }
+    }
+
+    fn device_identity(&mut self, identity: midi::sysex::Identity) -> Result<Vec<Msg>, Error> {
+        use crate::bytes::Displayable;
+        use connection::*;
+
+        if identity.manufacturer != [KORG_ID] || !identity.data.starts_with(&NANO_KONTROL2_FAMILY) {
+            return Err(Error::ManufacturerMismatch {
+                expected: Displayable::from([KORG_ID].as_slice()).to_owned(),
+                found: Displayable::from(
+                    [identity.manufacturer, identity.data].concat().as_slice(),
+                )
+                .to_owned(),
+            });
+        }
+
+        if self.state != State::Connecting(ConnectionStatus::DeviceQueried) {
+            // Keepalive reply.
+            return Ok(Msg::none());
+        }
+
+        log::debug!("Found nanoKONTROL2, requesting current scene");
+        self.global_chan = identity.device_id & 0x0f;
+        self.chan = midi::Channel::from(self.global_chan);
+        self.state = State::Connecting(ConnectionStatus::SceneRequested);
+
+        Ok(vec![
+            self.exclusive_msg(&SCENE_DUMP_REQUEST, &[]),
+            Msg::connetion_in_progress(),
+        ])
+    }
+
+    fn device_exclusive(&mut self, function: &[u8]) -> Result<Vec<Msg>, Error> {
+        use connection::*;
+        use ConnectionStatus::*;
+
+        match (self.state, function) {
+            (State::Connecting(SceneRequested), function) if function.starts_with(&SCENE_DUMP) => {
+                let mut scene = decode_7bit(&function[SCENE_DUMP.len()..]);
+                match scene.get_mut(LED_MODE_OFFSET) {
+                    Some(led_mode) if *led_mode == LED_MODE_EXTERNAL => Ok(self.device_connected()),
+                    Some(led_mode) => {
+                        log::debug!("Switching nanoKONTROL2 to external LED mode");
+                        *led_mode = LED_MODE_EXTERNAL;
+                        let msg = self.scene_dump(&scene);
+
+                        scene[LED_MODE_OFFSET] = LED_MODE_INTERNAL;
+                        self.orig_scene = Some(scene);
+                        self.state = State::Connecting(SceneSent);
+
+                        Ok(vec![msg, Msg::connetion_in_progress()])
+                    }
+                    None => Err(Error::ConnectionError),
+                }
+            }
+            (State::Connecting(SceneSent), function) if function == DATA_LOAD_COMPLETED => {
+                Ok(self.device_connected())
+            }
+            (State::Connecting(SceneSent), function) if function == DATA_LOAD_ERROR => {
+                self.orig_scene = None;
+                Err(Error::ConnectionError)
+            }
+            _ => Ok(Msg::none()),
+        }
+    }
+
+    fn device_connected(&mut self) -> Vec<Msg> {
+        log::debug!("Connected to nanoKONTROL2");
+        self.state = State::Connected;
+
+        let mut list = vec![Msg::from_connection_result(Ok(()))];
+        list.extend(self.reset());
+        list.extend(self.release_strips());
+        list.push(CtrlSurfEvent::DataRequest.to_app());
+
+        list
+    }
+
+    fn device_control(&mut self, id: u8, value: u8) -> Vec<Msg> {
+        use control::*;
+        use Mixer::*;
+        use Transport::*;
+
+        let pos = value.min(MAX) as f64 / MAX as f64;
+        match id {
+            FADER_FIRST..=FADER_LAST => {
+                let index = (id - FADER_FIRST) as usize;
+                return self.device_strip(index, Volume(self.fader_curve.to_volume(pos)));
+            }
+            KNOB_FIRST..=KNOB_LAST => {
+                let index = (id - KNOB_FIRST) as usize;
+                return self.device_strip(index, Balance(2.0 * pos - 1.0));
+            }
+            _ if value != PRESSED => (),
+            SOLO_FIRST..=SOLO_LAST => {
+                let index = (id - SOLO_FIRST) as usize;
+                if self.strips[index].name.is_some() {
+                    return CtrlSurfEvent::SelectPlayer(index).to_app().into();
+                }
+            }
+            MUTE_FIRST..=MUTE_LAST => {
+                let index = (id - MUTE_FIRST) as usize;
+                let event = if self.strips[index].is_muted {
+                    Unmute
+                } else {
+                    Mute
+                };
+                return self.device_strip(index, event);
+            }
+            PLAY => return PlayPause.to_app().into(),
+            STOP => return Stop.to_app().into(),
+            REWIND => return StepBackward(1).to_app().into(),
+            FAST_FWD => return StepForward(1).to_app().into(),
+            REC => return SetShuffle(!self.shuffle).to_app().into(),
+            CYCLE => return SetLoopStatus(self.loop_status.next()).to_app().into(),
+            TRACK_PREVIOUS => return Previous.to_app().into(),
+            TRACK_NEXT => return Next.to_app().into(),
+            MARKER_SET => return CtrlSurfEvent::Marker.to_app().into(),
+            MARKER_PREVIOUS => return Self::seek_to(self.markers.a),
+            MARKER_NEXT => return Self::seek_to(self.markers.b),
+            _ => (),
+        }
+
+        Msg::none()
+    }
+
+    fn device_strip(&self, index: usize, mixer: Mixer) -> Vec<Msg> {
+        if self.strips[index].name.is_none() {
+            return Msg::none();
+        }
+
+        CtrlSurfEvent::Strip(index, mixer).to_app().into()
+    }
+
+    fn seek_to(marker: Option<Duration>) -> Vec<Msg> {
+        match marker {
+            Some(pos) => Transport::SetPosition(pos).to_app().into(),
+            None => Msg::none(),
+        }
+    }
+}
+
+/// App events.
+impl NanoKontrol2 {
+    fn app_play(&mut self, is_playing: bool) -> Vec<Msg> {
+        self.is_playing = is_playing;
+
+        vec![
+            self.led(control::STOP, false),
+            self.led(control::PLAY, is_playing),
+        ]
+    }
+
+    fn app_strip(&mut self, index: usize, mixer: Mixer) -> Vec<Msg> {
+        use Mixer::*;
+
+        let is_muted = match mixer {
+            Mute => true,
+            Unmute => false,
+            Volume(_) | Balance(_) => return Msg::none(),
+        };
+
+        match self.strips.get_mut(index) {
+            Some(strip) => strip.is_muted = is_muted,
+            None => return Msg::none(),
+        }
+
+        self.led(control::MUTE_FIRST + index as u8, is_muted).into()
+    }
+
+    fn app_player_index(&mut self, index: usize) -> Vec<Msg> {
+        self.player_index = Some(index);
+        self.select_leds()
+    }
+
+    fn app_player_list(&mut self, list: &[Arc<str>]) -> Vec<Msg> {
+        let has_players = !list.is_empty();
+        let stop_led = (has_players != self.has_players).then(|| {
+            self.has_players = has_players;
+            self.led(control::STOP, !has_players)
+        });
+
+        let mut released = Vec::new();
+        for (index, strip) in self.strips.iter_mut().enumerate() {
+            let name = list.get(index).cloned();
+            if name != strip.name && strip.name.is_some() {
+                // Another player or no more players for this strip.
+                *strip = Strip::default();
+                released.push(index);
+            }
+            strip.name = name;
+        }
+
+        let mut msg_list = released
+            .into_iter()
+            .map(|index| self.led(control::MUTE_FIRST + index as u8, false))
+            .collect::<Vec<_>>();
+        msg_list.extend(stop_led);
+        msg_list.extend(self.select_leds());
+
+        msg_list
+    }
+
+    /// Unbinds the strips from the players.
+    fn release_strips(&mut self) -> Vec<Msg> {
+        self.strips = Default::default();
+
+        (0..STRIPS as u8)
+            .map(|index| self.led(control::MUTE_FIRST + index, false))
+            .collect()
+    }
+
+    fn select_leds(&self) -> Vec<Msg> {
+        (0..STRIPS)
+            .map(|index| {
+                let is_cur = self.strips[index].name.is_some() && self.player_index == Some(index);
+                self.led(control::SOLO_FIRST + index as u8, is_cur)
+            })
+            .collect()
+    }
+}
+
+/// Messages to device.
+impl NanoKontrol2 {
+    fn exclusive_msg(&self, function: &[u8], data: &[u8]) -> Msg {
+        use connection::*;
+
+        let mut payload = vec![KORG_ID, EXCLUSIVE_CHANNEL | self.global_chan];
+        payload.extend(EXCLUSIVE_HEADER);
+        payload.extend(function);
+        payload.extend(data);
+
+        midi::Msg::new_sysex(&payload).to_device()
+    }
+
+    fn scene_dump(&self, scene: &[u8]) -> Msg {
+        self.exclusive_msg(&connection::SCENE_DUMP, &encode_7bit(scene))
+    }
+
+    fn led(&self, id: u8, is_on: bool) -> Msg {
+        let value = if is_on { control::ON } else { control::OFF };
+        [control::TAG | self.chan, id, value].into()
+    }
+}
+
+/// Decodes Korg's 7 bits sysex data.
+///
+/// Each group of 7 bytes is preceded by a byte holding their most significant bits.
+fn decode_7bit(data: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(data.len() * 7 / 8);
+    for group in data.chunks(8) {
+        if let Some((msbs, bytes)) = group.split_first() {
+            for (idx, byte) in bytes.iter().enumerate() {
+                decoded.push(byte | (((msbs >> idx) & 1) << 7));
+            }
+        }
+    }
+
+    decoded
+}
+
+/// Encodes data to Korg's 7 bits sysex data.
+fn encode_7bit(data: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(data.len() * 8 / 7 + 1);
+    for group in data.chunks(7) {
+        let msbs = group
+            .iter()
+            .enumerate()
+            .fold(0, |msbs, (idx, byte)| msbs | ((byte >> 7) << idx));
+        encoded.push(msbs);
+        encoded.extend(group.iter().map(|byte| byte & 0x7f));
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_7bit_msbs() {
+        assert!(encode_7bit(&[]).is_empty());
+        assert_eq!(encode_7bit(&[0x80, 0x01, 0xff]), [0b101, 0x00, 0x01, 0x7f]);
+
+        // The 8th byte starts a new group.
+        let data = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x81];
+        assert_eq!(
+            encode_7bit(&data),
+            [0b100_0000, 0, 0, 0, 0, 0, 0, 0, 0b1, 0x01],
+        );
+    }
+
+    #[test]
+    fn decode_7bit_msbs() {
+        assert_eq!(decode_7bit(&[0b101, 0x00, 0x01, 0x7f]), [0x80, 0x01, 0xff]);
+    }
+
+    #[test]
+    fn round_trip_7bit() {
+        for len in 0..=22usize {
+            let data = (0..len)
+                .map(|idx| (idx as u8).wrapping_mul(37) ^ 0xa5)
+                .collect::<Vec<u8>>();
+
+            let encoded = encode_7bit(&data);
+            assert!(encoded.iter().all(|byte| byte & 0x80 == 0));
+            assert_eq!(encoded.len(), len + len.div_ceil(7));
+            assert_eq!(decode_7bit(&encoded), data);
+        }
+    }
+}
@@ -0,0 +1,479 @@
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    ctrl_surf::{
+        event::{self, *},
+        ControlSurface, FaderCurve, LoopStatus, Msg,
+    },
+    midi, osc,
+};
+
+/// Threshold above which a button value is considered as pressed.
+const PRESSED_THRSD: f64 = 0.5;
+
+/// Max registered clients, the least recently seen client is dropped above this.
+const MAX_CLIENTS: usize = 16;
+/// Clients which didn't send anything for this long are dropped.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const CLIENT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+mod addr {
+    pub const REGISTER: &str = "/register";
+    pub const UNREGISTER: &str = "/unregister";
+
+    pub const PLAY: &str = "/transport/play";
+    pub const STOP: &str = "/transport/stop";
+    pub const PREVIOUS: &str = "/transport/previous";
+    pub const NEXT: &str = "/transport/next";
+    pub const STEP_BACKWARD: &str = "/transport/step_backward";
+    pub const STEP_FORWARD: &str = "/transport/step_forward";
+    pub const POSITION: &str = "/transport/position";
+    pub const SHUFFLE: &str = "/transport/shuffle";
+    pub const LOOP: &str = "/transport/loop";
+    pub const MARKER: &str = "/transport/marker";
+
+    pub const VOLUME: &str = "/mixer/volume";
+    pub const MUTE: &str = "/mixer/mute";
+    pub const BALANCE: &str = "/mixer/balance";
+
+    pub const PLAYER_SELECT: &str = "/player/select";
+
+    pub const TRACK_TITLE: &str = "/track/title";
+    pub const TRACK_ARTIST: &str = "/track/artist";
+    pub const TRACK_ALBUM: &str = "/track/album";
+    pub const TRACK_DURATION: &str = "/track/duration";
+}
+
+/// Control Surface driven by OSC clients such as TouchOSC or Open Stage Control.
+///
+/// Buttons trigger their action when received without argument
+/// or with a value of at least 0.5, so that releases are ignored.
+/// `/transport/shuffle` & `/mixer/mute` set the state to their argument
+/// if any and toggle it otherwise.
+///
+/// Positions & durations are expressed in seconds, the volume is a fader
+/// position in the range `0.0..=1.0` and the balance is in `-1.0..=1.0`.
+///
+/// The app state is sent back to the registered clients using the same addresses,
+/// as well as the current track under `/track/`. A client is registered when it
+/// sends a message. Clients listening on a port other than their sending port
+/// can send `/register <port>`, after which the other messages from their host
+/// don't register the sending port. Clients which remain silent for 30mn are dropped,
+/// as is the least recently seen client when more than 16 clients are registered.
+#[derive(Debug)]
+pub struct Osc {
+    is_connected: bool,
+    /// Registered clients, by reply address.
+    clients: BTreeMap<SocketAddr, Client>,
+    is_muted: bool,
+    shuffle: bool,
+    loop_status: LoopStatus,
+    volume: Option<f64>,
+    fader_curve: FaderCurve,
+}
+
+#[derive(Debug)]
+struct Client {
+    last_seen: Instant,
+    /// Sending address of a `/register <port>` on another port.
+    registered_from: Option<SocketAddr>,
+}
+
+impl Client {
+    /// Whether the messages from `sender` come from this client.
+    fn is_sent_by(&self, addr: SocketAddr, sender: SocketAddr) -> bool {
+        addr == sender || self.registered_from == Some(sender)
+    }
+}
+
+impl Default for Osc {
+    fn default() -> Self {
+        Self {
+            is_connected: false,
+            clients: BTreeMap::new(),
+            is_muted: false,
+            shuffle: false,
+            loop_status: LoopStatus::None,
+            volume: None,
+            fader_curve: FaderCurve::default(),
+        }
+    }
+}
+
+impl ControlSurface for Osc {
+    fn start_connection(&mut self) -> Vec<Msg> {
+        log::debug!("Waiting for OSC clients");
+
+        // The socket is bound by the app, so the Control Surface is ready.
+        self.is_connected = true;
+
+        vec![
+            Msg::from_connection_result(Ok(())),
+            CtrlSurfEvent::DataRequest.to_app(),
+            Msg::schedule_tick(CLIENT_CHECK_INTERVAL),
+        ]
+    }
+
+    fn abort_connection(&mut self) -> Vec<Msg> {
+        Msg::none()
+    }
+
+    fn msg_from_device(&mut self, _msg: midi::Msg) -> Vec<Msg> {
+        Msg::none()
+    }
+
+    fn msg_from_osc(&mut self, client: SocketAddr, msg: osc::Msg) -> Vec<Msg> {
+        use addr::*;
+
+        if !self.is_connected {
+            return Msg::none();
+        }
+
+        let now = Instant::now();
+        for (_, info) in self
+            .clients
+            .iter_mut()
+            .filter(|(addr, info)| info.is_sent_by(**addr, client))
+        {
+            info.last_seen = now;
+        }
+
+        let arg = msg.args().first().and_then(osc::Arg::as_f64);
+        match msg.addr() {
+            REGISTER => {
+                let addr = Self::client_addr(client, arg);
+                return self.register(addr, (addr != client).then_some(client));
+            }
+            UNREGISTER => {
+                let client = Self::client_addr(client, arg);
+                if self.clients.remove(&client).is_some() {
+                    log::info!("Unregistered OSC client {client}");
+                }
+                return Msg::none();
+            }
+            _ => (),
+        }
+
+        // Replies to this host go to the port it registered.
+        let has_registered_port = self
+            .clients
+            .iter()
+            .any(|(addr, info)| info.registered_from.is_some() && addr.ip() == client.ip());
+
+        let mut list = if has_registered_port {
+            Msg::none()
+        } else {
+            self.register(client, None)
+        };
+        list.extend(self.client_event(&msg, arg));
+
+        list
+    }
+
+    fn event_from_app(&mut self, event: AppEvent) -> Vec<Msg> {
+        use addr::*;
+
+        if !self.is_connected {
+            log::debug!("Ignoring App event: Control surface not connected.");
+            return Msg::none();
+        }
+
+        use AppEvent::*;
+        match event {
+            Transport(event) => {
+                use event::Transport::*;
+                match event {
+                    Play => return self.broadcast(osc::Msg::new(PLAY).with(flag(true))),
+                    Pause => return self.broadcast(osc::Msg::new(PLAY).with(flag(false))),
+                    Stop => return self.reset(),
+                    _ => (),
+                }
+            }
+            Mixer(mixer) => {
+                use event::Mixer::*;
+                match mixer {
+                    Volume(vol) => return self.app_volume(vol),
+                    Mute => return self.app_muted(true),
+                    Unmute => return self.app_muted(false),
+                    Balance(balance) => {
+                        return self.broadcast(osc::Msg::new(BALANCE).with(balance as f32))
+                    }
+                }
+            }
+            NewApp(app) => {
+                log::debug!("New application {app}");
+                return CtrlSurfEvent::DataRequest.to_app().into();
+            }
+            Data(data) => {
+                use event::Data::*;
+                match data {
+                    Track(track) => return self.app_track(&track),
+                    Position(pos) => {
+                        return self.broadcast(osc::Msg::new(POSITION).with(pos.as_secs_f32()))
+                    }
+                    PlaybackStatus(status) => {
+                        let is_playing = status.is_playing();
+                        return self.broadcast(osc::Msg::new(PLAY).with(flag(is_playing)));
+                    }
+                    Shuffle(shuffle) => {
+                        self.shuffle = shuffle;
+                        return self.broadcast(osc::Msg::new(SHUFFLE).with(flag(shuffle)));
+                    }
+                    LoopStatus(status) => {
+                        self.loop_status = status;
+                        let is_looping = status != crate::ctrl_surf::LoopStatus::None;
+                        return self.broadcast(osc::Msg::new(LOOP).with(flag(is_looping)));
+                    }
+                    Markers(markers) => {
+                        let has_markers = !markers.is_empty();
+                        return self.broadcast(osc::Msg::new(MARKER).with(flag(has_markers)));
+                    }
+                    PlayerIndex(index) => {
+                        return self.broadcast(osc::Msg::new(PLAYER_SELECT).with(index as i32))
+                    }
+                    _ => (),
+                }
+            }
+            Strip(..) => (),
+        }
+
+        Msg::none()
+    }
+
+    fn set_fader_curve(&mut self, curve: FaderCurve) -> Vec<Msg> {
+        self.fader_curve = curve;
+
+        match self.volume {
+            Some(vol) if self.is_connected => {
+                let pos = curve.to_position(vol) as f32;
+                self.broadcast(osc::Msg::new(addr::VOLUME).with(pos))
+            }
+            _ => Msg::none(),
+        }
+    }
+
+    fn uses_osc(&self) -> bool {
+        true
+    }
+
+    fn is_connected(&self) -> bool {
+        self.is_connected
+    }
+
+    fn reset(&mut self) -> Vec<Msg> {
+        use addr::*;
+
+        self.is_muted = false;
+        self.shuffle = false;
+        self.loop_status = LoopStatus::None;
+        self.volume = None;
+
+        let mut list = Vec::new();
+        for addr in [PLAY, MUTE, SHUFFLE, LOOP, MARKER, VOLUME, POSITION, BALANCE] {
+            list.extend(self.broadcast(osc::Msg::new(addr).with(0.0f32)));
+        }
+        for addr in [TRACK_TITLE, TRACK_ARTIST, TRACK_ALBUM] {
+            list.extend(self.broadcast(osc::Msg::new(addr).with("")));
+        }
+        list.extend(self.broadcast(osc::Msg::new(TRACK_DURATION).with(0.0f32)));
+
+        list
+    }
+
+    fn disconnect(&mut self) -> Vec<Msg> {
+        if !self.is_connected {
+            return Msg::none();
+        }
+
+        log::debug!("Disconnecting from OSC clients");
+
+        let list = self.reset();
+        self.clients.clear();
+        self.is_connected = false;
+
+        list
+    }
+
+    fn tick(&mut self) -> Vec<Msg> {
+        if !self.is_connected {
+            return Msg::none();
+        }
+
+        self.clients.retain(|client, info| {
+            let is_alive = info.last_seen.elapsed() < CLIENT_TIMEOUT;
+            if !is_alive {
+                log::info!("OSC client {client} timed out");
+            }
+            is_alive
+        });
+
+        Msg::schedule_tick(CLIENT_CHECK_INTERVAL).into()
+    }
+}
+
+/// Client events.
+impl Osc {
+    /// Returns the address to reply to, using `port` if specified.
+    fn client_addr(client: SocketAddr, port: Option<f64>) -> SocketAddr {
+        match port {
+            Some(port) if (1.0..=u16::MAX as f64).contains(&port) => {
+                SocketAddr::new(client.ip(), port as u16)
+            }
+            _ => client,
+        }
+    }
+
+    /// Registers `client`, `registered_from` being the sending address
+    /// of a `/register <port>` on another port.
+    fn register(&mut self, client: SocketAddr, registered_from: Option<SocketAddr>) -> Vec<Msg> {
+        if let Some(from) = registered_from {
+            // The sending port no longer needs the replies.
+            if matches!(self.clients.get(&from), Some(info) if info.registered_from.is_none()) {
+                self.clients.remove(&from);
+                log::debug!("Replacing OSC client {from} with {client}");
+            }
+        }
+
+        let info = Client {
+            last_seen: Instant::now(),
+            registered_from,
+        };
+        if self.clients.insert(client, info).is_some() {
+            return Msg::none();
+        }
+
+        log::info!("Registered OSC client {client}");
+
+        if self.clients.len() > MAX_CLIENTS {
+            let oldest = self
+                .clients
+                .iter()
+                .min_by_key(|(_, info)| info.last_seen)
+                .map(|(addr, _)| *addr);
+            if let Some(oldest) = oldest {
+                self.clients.remove(&oldest);
+                log::warn!("Too many OSC clients, dropped {oldest}");
+            }
+        }
+
+        // Get the whole state for the new client.
+        CtrlSurfEvent::DataRequest.to_app().into()
+    }
+
+    fn client_event(&mut self, msg: &osc::Msg, arg: Option<f64>) -> Vec<Msg> {
+        use addr::*;
+        use Transport::*;
+
+        let is_pressed = !matches!(arg, Some(value) if value < PRESSED_THRSD);
+        match msg.addr() {
+            POSITION => {
+                let pos = arg
+                    .filter(|secs| secs.is_finite())
+                    .and_then(|secs| Duration::try_from_secs_f64(secs.max(0.0)).ok());
+                if let Some(pos) = pos {
+                    return SetPosition(pos).to_app().into();
+                }
+            }
+            VOLUME => {
+                if let Some(pos) = arg.filter(|pos| !pos.is_nan()) {
+                    let vol = self.fader_curve.to_volume(pos.clamp(0.0, 1.0));
+                    self.volume = Some(vol);
+                    return Mixer::Volume(vol).to_app().into();
+                }
+            }
+            BALANCE => {
+                if let Some(balance) = arg.filter(|balance| !balance.is_nan()) {
+                    return Mixer::Balance(balance.clamp(-1.0, 1.0)).to_app().into();
+                }
+            }
+            SHUFFLE => {
+                let shuffle = arg.map_or(!self.shuffle, |value| value >= PRESSED_THRSD);
+                return SetShuffle(shuffle).to_app().into();
+            }
+            MUTE => {
+                let is_muted = arg.map_or(!self.is_muted, |value| value >= PRESSED_THRSD);
+                if is_muted {
+                    return Mixer::Mute.to_app().into();
+                } else {
+                    return Mixer::Unmute.to_app().into();
+                }
+            }
+            PLAYER_SELECT => {
+                if let Some(index) = arg.filter(|index| *index >= 0.0) {
+                    return CtrlSurfEvent::SelectPlayer(index as usize).to_app().into();
+                }
+            }
+            _ if !is_pressed => (),
+            PLAY => return PlayPause.to_app().into(),
+            STOP => return Stop.to_app().into(),
+            PREVIOUS => return Previous.to_app().into(),
+            NEXT => return Next.to_app().into(),
+            STEP_BACKWARD => return StepBackward(1).to_app().into(),
+            STEP_FORWARD => return StepForward(1).to_app().into(),
+            LOOP => return SetLoopStatus(self.loop_status.next()).to_app().into(),
+            MARKER => return CtrlSurfEvent::Marker.to_app().into(),
+            other => log::debug!("Unknown OSC address {other}"),
+        }
+
+        Msg::none()
+    }
+}
+
+/// App events.
+impl Osc {
+    fn app_muted(&mut self, is_muted: bool) -> Vec<Msg> {
+        self.is_muted = is_muted;
+        self.broadcast(osc::Msg::new(addr::MUTE).with(flag(is_muted)))
+    }
+
+    fn app_volume(&mut self, vol: f64) -> Vec<Msg> {
+        // Don't send back the volume a client just set,
+        // it would fight with the user moving the fader.
+        if matches!(self.volume, Some(cur) if (cur - vol).abs() < f64::EPSILON) {
+            return Msg::none();
+        }
+
+        self.volume = Some(vol);
+        let pos = self.fader_curve.to_position(vol) as f32;
+        self.broadcast(osc::Msg::new(addr::VOLUME).with(pos))
+    }
+
+    fn app_track(&self, track: &crate::ctrl_surf::Track) -> Vec<Msg> {
+        use addr::*;
+
+        let mut list = Vec::new();
+        for (addr, field) in [
+            (TRACK_TITLE, &track.title),
+            (TRACK_ARTIST, &track.artist),
+            (TRACK_ALBUM, &track.album),
+        ] {
+            let value = field.as_deref().unwrap_or_default();
+            list.extend(self.broadcast(osc::Msg::new(addr).with(value)));
+        }
+
+        let duration = track.duration.unwrap_or_default().as_secs_f32();
+        list.extend(self.broadcast(osc::Msg::new(TRACK_DURATION).with(duration)));
+
+        list
+    }
+
+    fn broadcast(&self, msg: osc::Msg) -> Vec<Msg> {
+        self.clients
+            .keys()
+            .map(|client| Msg::ToOsc(*client, msg.clone()))
+            .collect()
+    }
+}
+
+/// Value for toggle widgets.
+fn flag(is_on: bool) -> f32 {
+    if is_on {
+        1.0
+    } else {
+        0.0
+    }
+}
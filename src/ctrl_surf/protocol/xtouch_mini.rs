@@ -0,0 +1,636 @@
+use std::sync::Arc;
+
+use crate::{
+    ctrl_surf::{
+        event::{self, *},
+        ControlSurface, Error, FaderCurve, LoopStatus, Markers, Msg,
+    },
+    midi,
+};
+
+mod connection {
+    pub const BEHRINGER_ID: [u8; 3] = [0x00, 0x20, 0x32];
+}
+
+/// Default global channel (11) used in standard mode.
+const CHANNEL: midi::Channel = midi::Channel::from(10);
+
+mod button {
+    use crate::midi::Tag;
+    pub const NOTE_ON: Tag = Tag::from(0x90);
+
+    pub const PRESSED: u8 = 127;
+    pub const OFF: u8 = 0;
+    pub const ON: u8 = 1;
+    pub const BLINK: u8 = 2;
+
+    // Layer A
+    pub const VOLUME_PUSH: u8 = 0;
+    pub const BALANCE_PUSH: u8 = 1;
+    pub const PREVIOUS: u8 = 8;
+    pub const NEXT: u8 = 9;
+    pub const STOP: u8 = 10;
+    pub const PLAY: u8 = 11;
+    pub const MUTE: u8 = 12;
+    pub const SHUFFLE: u8 = 13;
+    pub const LOOP: u8 = 14;
+    pub const MARKER: u8 = 15;
+    pub const STEP_BACKWARD: u8 = 16;
+    pub const STEP_FORWARD: u8 = 17;
+
+    // Layer B
+    pub const STRIP_MUTE_FIRST: u8 = 32;
+    pub const STRIP_MUTE_LAST: u8 = 39;
+    pub const STRIP_SELECT_FIRST: u8 = 40;
+    pub const STRIP_SELECT_LAST: u8 = 47;
+}
+
+mod control {
+    use crate::midi::Tag;
+    pub const TAG: Tag = Tag::from(0xb0);
+
+    // Layer A
+    pub const VOLUME_ENC: u8 = 1;
+    pub const BALANCE_ENC: u8 = 2;
+    pub const FADER_A: u8 = 9;
+
+    // Layer B
+    pub const FADER_B: u8 = 10;
+    pub const STRIP_ENC_FIRST: u8 = 11;
+    pub const STRIP_ENC_LAST: u8 = 18;
+
+    pub const MAX: u8 = 127;
+}
+
+const STRIPS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+
+/// An encoder bound to a player in layer B.
+#[derive(Clone, Debug, Default)]
+struct Strip {
+    name: Option<Arc<str>>,
+    is_muted: bool,
+    /// Last volume set from the device or the app.
+    volume: Option<f64>,
+}
+
+/// Behringer X-Touch Mini in standard mode.
+///
+/// Layer A controls the current player:
+///
+/// - Encoders 1 & 2 control the volume & balance, pushing them
+///   toggles mute & centers the balance.
+/// - The top buttons are previous, next, stop, play / pause, mute,
+///   shuffle, loop & A-B loop marker.
+/// - The two first bottom buttons step backward & forward.
+///
+/// Layer B binds each encoder to the volume of a player from the players list.
+/// The top buttons toggle mute and the bottom buttons select the player.
+///
+/// In both layers, the fader controls the volume of the current player.
+#[derive(Debug)]
+pub struct XTouchMini {
+    state: State,
+    /// Whether a keepalive identity request is waiting for a device message.
+    is_keepalive_pending: bool,
+    is_muted: bool,
+    shuffle: bool,
+    loop_status: LoopStatus,
+    /// Last volume set from the device or the app.
+    volume: Option<f64>,
+    balance: f64,
+    fader_curve: FaderCurve,
+    player_index: Option<usize>,
+    /// Whether the players list is not empty.
+    has_players: bool,
+    strips: [Strip; STRIPS],
+}
+
+impl Default for XTouchMini {
+    fn default() -> Self {
+        Self {
+            state: State::Disconnected,
+            is_keepalive_pending: false,
+            is_muted: false,
+            shuffle: false,
+            loop_status: LoopStatus::None,
+            volume: None,
+            balance: 0.0,
+            fader_curve: FaderCurve::default(),
+            player_index: None,
+            has_players: false,
+            strips: Default::default(),
+        }
+    }
+}
+
+impl ControlSurface for XTouchMini {
+    fn start_connection(&mut self) -> Vec<Msg> {
+        log::debug!("Attempt to connect to X-Touch Mini");
+
+        self.state = State::Connecting;
+        self.is_keepalive_pending = false;
+
+        vec![
+            midi::sysex::identity_request().to_device(),
+            Msg::connetion_in_progress(),
+        ]
+    }
+
+    fn abort_connection(&mut self) -> Vec<Msg> {
+        if self.state == State::Connecting {
+            log::debug!("Aborting connection to X-Touch Mini");
+
+            self.state = State::Disconnected;
+        }
+
+        Msg::none()
+    }
+
+    fn msg_from_device(&mut self, msg: midi::Msg) -> Vec<Msg> {
+        // Any message proves that the device is still there.
+        self.is_keepalive_pending = false;
+
+        let buf = msg.inner();
+        let &tag_chan = match buf.first() {
+            Some(tag_chan) => tag_chan,
+            None => return Msg::none(),
+        };
+
+        match midi::Tag::from_tag_chan(tag_chan) {
+            button::NOTE_ON => {
+                if let Some(&[id, button::PRESSED]) = buf.get(1..=2) {
+                    return self.device_button_pressed(id);
+                }
+            }
+            control::TAG => {
+                if let Some(&[id, value]) = buf.get(1..=2) {
+                    return self.device_control(id, value);
+                }
+            }
+            midi::sysex::TAG => {
+                return self
+                    .device_identity(&msg)
+                    .unwrap_or_else(|err| Msg::from_connection_result(Err(err)).into());
+            }
+            _ => (),
+        }
+
+        Msg::none()
+    }
+
+    fn event_from_app(&mut self, event: AppEvent) -> Vec<Msg> {
+        if !self.is_connected() {
+            log::debug!("Ignoring App event: Control surface not connected.");
+            return Msg::none();
+        }
+
+        use AppEvent::*;
+        match event {
+            Transport(event) => {
+                use event::Transport::*;
+                match event {
+                    Play => return self.app_play(true),
+                    Pause => return self.app_play(false),
+                    Stop => return self.reset(),
+                    _ => (),
+                }
+            }
+            Mixer(mixer) => {
+                use event::Mixer::*;
+                match mixer {
+                    Volume(vol) => return self.app_volume(vol),
+                    Mute => return self.app_mute(true),
+                    Unmute => return self.app_mute(false),
+                    Balance(balance) => return self.app_balance(balance),
+                }
+            }
+            Strip(index, mixer) => return self.app_strip(index, mixer),
+            NewApp(app) => {
+                log::debug!("New application {app}");
+                return CtrlSurfEvent::DataRequest.to_app().into();
+            }
+            Data(data) => {
+                use event::Data::*;
+                match data {
+                    PlaybackStatus(status) => {
+                        use crate::ctrl_surf::data::PlaybackStatus::*;
+
+                        match status {
+                            Playing => return self.app_play(true),
+                            Paused => return self.app_play(false),
+                            Stopped => return self.reset(),
+                        }
+                    }
+                    Shuffle(shuffle) => return self.app_shuffle(shuffle),
+                    LoopStatus(status) => return self.app_loop_status(status),
+                    Markers(markers) => return self.app_markers(markers),
+                    PlayerIndex(index) => return self.app_player_index(index),
+                    PlayerList(list) => return self.app_player_list(&list),
+                    _ => (),
+                }
+            }
+        }
+
+        Msg::none()
+    }
+
+    fn keepalive(&mut self) -> Vec<Msg> {
+        if !self.is_connected() {
+            return Msg::none();
+        }
+
+        if self.is_keepalive_pending {
+            log::warn!("X-Touch Mini stopped answering");
+            self.is_keepalive_pending = false;
+            self.state = State::Disconnected;
+
+            return Msg::connection_lost().into();
+        }
+
+        self.is_keepalive_pending = true;
+        midi::sysex::identity_request().to_device().into()
+    }
+
+    fn set_fader_curve(&mut self, curve: FaderCurve) -> Vec<Msg> {
+        self.fader_curve = curve;
+
+        if !self.is_connected() {
+            return Msg::none();
+        }
+
+        let mut list = Vec::new();
+        if let Some(vol) = self.volume {
+            list.push(Self::volume_ring_msg(curve, control::VOLUME_ENC, vol));
+        }
+        for (index, strip) in self.strips.iter().enumerate() {
+            if let Some(vol) = strip.volume {
+                let id = control::STRIP_ENC_FIRST + index as u8;
+                list.push(Self::volume_ring_msg(curve, id, vol));
+            }
+        }
+
+        list
+    }
+
+    fn is_connected(&self) -> bool {
+        self.state == State::Connected
+    }
+
+    fn reset(&mut self) -> Vec<Msg> {
+        use button::*;
+
+        let mut list = vec![
+            Self::led_msg(PREVIOUS, OFF),
+            Self::led_msg(NEXT, OFF),
+            Self::led_msg(STOP, self.stop_led_value()),
+            Self::led_msg(PLAY, OFF),
+            Self::led_msg(MUTE, OFF),
+            Self::led_msg(SHUFFLE, OFF),
+            Self::led_msg(LOOP, OFF),
+            Self::led_msg(MARKER, OFF),
+            Self::control_msg(control::VOLUME_ENC, 0),
+            Self::control_msg(control::BALANCE_ENC, control::MAX / 2),
+        ];
+
+        for index in 0..STRIPS as u8 {
+            list.push(Self::led_msg(STRIP_SELECT_FIRST + index, OFF));
+        }
+
+        // The strips stay bound to their players.
+        self.is_muted = false;
+        self.shuffle = false;
+        self.loop_status = LoopStatus::None;
+        self.volume = None;
+        self.balance = 0.0;
+        self.player_index = None;
+
+        list
+    }
+
+    fn disconnect(&mut self) -> Vec<Msg> {
+        if !self.is_connected() {
+            return Msg::none();
+        }
+
+        log::debug!("Disconnecting from X-Touch Mini");
+
+        let mut list = self.reset();
+        list.extend(self.release_strips());
+        list.push(Self::led_msg(button::STOP, button::OFF));
+
+        self.state = State::Disconnected;
+
+        list
+    }
+}
+
+/// Device events.
+impl XTouchMini {
+    fn device_identity(&mut self, msg: &midi::Msg) -> Result<Vec<Msg>, Error> {
+        use crate::bytes::Displayable;
+        use connection::*;
+        use Error::*;
+
+        let payload = msg.parse_sysex()?;
+
+        let identity = match midi::sysex::parse_identity_reply(payload) {
+            Some(identity) => identity,
+            None => return Err(UnexpectedDeviceMsg(msg.display().to_owned())),
+        };
+
+        if identity.manufacturer != BEHRINGER_ID {
+            return Err(ManufacturerMismatch {
+                expected: Displayable::from(BEHRINGER_ID.as_slice()).to_owned(),
+                found: Displayable::from(identity.manufacturer).to_owned(),
+            });
+        }
+
+        if self.state != State::Connecting {
+            // Keepalive reply.
+            return Ok(Msg::none());
+        }
+
+        log::debug!("Connected to X-Touch Mini");
+        self.state = State::Connected;
+
+        let mut list = vec![Msg::from_connection_result(Ok(()))];
+        list.extend(self.reset());
+        list.extend(self.release_strips());
+        list.push(CtrlSurfEvent::DataRequest.to_app());
+
+        Ok(list)
+    }
+
+    fn device_button_pressed(&mut self, id: u8) -> Vec<Msg> {
+        use button::*;
+        use Mixer::*;
+        use Transport::*;
+
+        match id {
+            VOLUME_PUSH | MUTE => {
+                if self.is_muted {
+                    return Unmute.to_app().into();
+                } else {
+                    return Mute.to_app().into();
+                }
+            }
+            BALANCE_PUSH => return Balance(0.0).to_app().into(),
+            PREVIOUS => return Previous.to_app().into(),
+            NEXT => return Next.to_app().into(),
+            STOP => return Stop.to_app().into(),
+            PLAY => return PlayPause.to_app().into(),
+            SHUFFLE => return SetShuffle(!self.shuffle).to_app().into(),
+            LOOP => return SetLoopStatus(self.loop_status.next()).to_app().into(),
+            MARKER => return CtrlSurfEvent::Marker.to_app().into(),
+            STEP_BACKWARD => return StepBackward(1).to_app().into(),
+            STEP_FORWARD => return StepForward(1).to_app().into(),
+            STRIP_MUTE_FIRST..=STRIP_MUTE_LAST => {
+                let index = (id - STRIP_MUTE_FIRST) as usize;
+                let strip = &self.strips[index];
+                if strip.name.is_some() {
+                    let event = if strip.is_muted { Unmute } else { Mute };
+                    return CtrlSurfEvent::Strip(index, event).to_app().into();
+                }
+            }
+            STRIP_SELECT_FIRST..=STRIP_SELECT_LAST => {
+                let index = (id - STRIP_SELECT_FIRST) as usize;
+                if self.strips[index].name.is_some() {
+                    return CtrlSurfEvent::SelectPlayer(index).to_app().into();
+                }
+            }
+            _ => (),
+        }
+
+        Msg::none()
+    }
+
+    fn device_control(&mut self, id: u8, value: u8) -> Vec<Msg> {
+        use control::*;
+        use Mixer::*;
+
+        let pos = value.min(MAX) as f64 / MAX as f64;
+        match id {
+            VOLUME_ENC | FADER_A | FADER_B => {
+                let vol = self.fader_curve.to_volume(pos);
+                self.volume = Some(vol);
+                return Volume(vol).to_app().into();
+            }
+            BALANCE_ENC => {
+                self.balance = 2.0 * pos - 1.0;
+                return Balance(self.balance).to_app().into();
+            }
+            STRIP_ENC_FIRST..=STRIP_ENC_LAST => {
+                let index = (id - STRIP_ENC_FIRST) as usize;
+                let strip = &mut self.strips[index];
+                if strip.name.is_some() {
+                    let vol = self.fader_curve.to_volume(pos);
+                    strip.volume = Some(vol);
+                    return CtrlSurfEvent::Strip(index, Volume(vol)).to_app().into();
+                }
+            }
+            _ => (),
+        }
+
+        Msg::none()
+    }
+}
+
+/// App events.
+impl XTouchMini {
+    fn app_play(&mut self, is_playing: bool) -> Vec<Msg> {
+        use button::*;
+
+        vec![
+            Self::led_msg(STOP, OFF),
+            Self::led_msg(PLAY, if is_playing { ON } else { BLINK }),
+        ]
+    }
+
+    fn app_mute(&mut self, is_muted: bool) -> Vec<Msg> {
+        self.is_muted = is_muted;
+        Self::led_msg(button::MUTE, Self::led_value(is_muted)).into()
+    }
+
+    fn app_volume(&mut self, vol: f64) -> Vec<Msg> {
+        // Don't send back the volume the device just set.
+        if matches!(self.volume, Some(cur) if (cur - vol).abs() < f64::EPSILON) {
+            return Msg::none();
+        }
+
+        self.volume = Some(vol);
+        Self::volume_ring_msg(self.fader_curve, control::VOLUME_ENC, vol).into()
+    }
+
+    fn app_balance(&mut self, balance: f64) -> Vec<Msg> {
+        if (self.balance - balance).abs() < f64::EPSILON {
+            return Msg::none();
+        }
+
+        self.balance = balance;
+        let value = ((balance + 1.0) / 2.0 * control::MAX as f64).round() as u8;
+        Self::control_msg(control::BALANCE_ENC, value).into()
+    }
+
+    fn app_shuffle(&mut self, shuffle: bool) -> Vec<Msg> {
+        self.shuffle = shuffle;
+        Self::led_msg(button::SHUFFLE, Self::led_value(shuffle)).into()
+    }
+
+    /// LOOP blinks when looping the track and is lit when looping the playlist.
+    fn app_loop_status(&mut self, status: LoopStatus) -> Vec<Msg> {
+        use button::*;
+
+        self.loop_status = status;
+        let state = match status {
+            LoopStatus::None => OFF,
+            LoopStatus::Track => BLINK,
+            LoopStatus::Playlist => ON,
+        };
+
+        Self::led_msg(LOOP, state).into()
+    }
+
+    /// MARKER blinks when only the A marker is set and is lit with both markers.
+    fn app_markers(&mut self, markers: Markers) -> Vec<Msg> {
+        use button::*;
+
+        let state = match (markers.a, markers.b) {
+            (Some(_), Some(_)) => ON,
+            (Some(_), None) => BLINK,
+            _ => OFF,
+        };
+
+        Self::led_msg(MARKER, state).into()
+    }
+
+    fn app_player_index(&mut self, index: usize) -> Vec<Msg> {
+        self.player_index = Some(index);
+        self.select_leds()
+    }
+
+    fn app_player_list(&mut self, list: &[Arc<str>]) -> Vec<Msg> {
+        use button::*;
+
+        let mut msg_list = Vec::new();
+
+        let has_players = !list.is_empty();
+        if has_players != self.has_players {
+            self.has_players = has_players;
+            msg_list.push(Self::led_msg(STOP, self.stop_led_value()));
+        }
+
+        for (index, strip) in self.strips.iter_mut().enumerate() {
+            let name = list.get(index).cloned();
+            if name != strip.name && strip.name.is_some() {
+                // Another player or no more players for this strip.
+                *strip = Strip::default();
+                msg_list.push(Self::led_msg(STRIP_MUTE_FIRST + index as u8, OFF));
+                msg_list.push(Self::control_msg(control::STRIP_ENC_FIRST + index as u8, 0));
+            }
+            strip.name = name;
+        }
+
+        msg_list.extend(self.select_leds());
+
+        msg_list
+    }
+
+    fn app_strip(&mut self, index: usize, mixer: Mixer) -> Vec<Msg> {
+        use Mixer::*;
+
+        let strip = match self.strips.get_mut(index) {
+            Some(strip) => strip,
+            None => return Msg::none(),
+        };
+
+        match mixer {
+            Volume(vol) => {
+                if matches!(strip.volume, Some(cur) if (cur - vol).abs() < f64::EPSILON) {
+                    return Msg::none();
+                }
+
+                strip.volume = Some(vol);
+                Self::volume_ring_msg(
+                    self.fader_curve,
+                    control::STRIP_ENC_FIRST + index as u8,
+                    vol,
+                )
+                .into()
+            }
+            Mute | Unmute => {
+                strip.is_muted = matches!(mixer, Mute);
+                let value = Self::led_value(strip.is_muted);
+                Self::led_msg(button::STRIP_MUTE_FIRST + index as u8, value).into()
+            }
+            Balance(_) => Msg::none(),
+        }
+    }
+
+    /// Unbinds the strips from the players.
+    fn release_strips(&mut self) -> Vec<Msg> {
+        use button::*;
+
+        self.strips = Default::default();
+
+        let mut list = Vec::new();
+        for index in 0..STRIPS as u8 {
+            list.push(Self::led_msg(STRIP_MUTE_FIRST + index, OFF));
+            list.push(Self::control_msg(control::STRIP_ENC_FIRST + index, 0));
+        }
+
+        list
+    }
+
+    fn select_leds(&self) -> Vec<Msg> {
+        (0..STRIPS)
+            .map(|index| {
+                let is_cur = self.strips[index].name.is_some() && self.player_index == Some(index);
+                Self::led_msg(
+                    button::STRIP_SELECT_FIRST + index as u8,
+                    Self::led_value(is_cur),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Messages to device.
+impl XTouchMini {
+    fn led_msg(id: u8, state: u8) -> Msg {
+        [button::NOTE_ON | CHANNEL, id, state].into()
+    }
+
+    /// STOP blinks while no player is available.
+    fn stop_led_value(&self) -> u8 {
+        if self.has_players {
+            button::OFF
+        } else {
+            button::BLINK
+        }
+    }
+
+    fn led_value(is_on: bool) -> u8 {
+        if is_on {
+            button::ON
+        } else {
+            button::OFF
+        }
+    }
+
+    fn control_msg(id: u8, value: u8) -> Msg {
+        [control::TAG | CHANNEL, id, value].into()
+    }
+
+    /// Sets the encoder `id` value & LED ring after the volume.
+    fn volume_ring_msg(curve: FaderCurve, id: u8, vol: f64) -> Msg {
+        let pos = curve.to_position(vol);
+        Self::control_msg(id, (pos * control::MAX as f64).round() as u8)
+    }
+}
@@ -5,6 +5,9 @@ pub use ctrl_surf::ControlSurface;
 
 pub mod midi;
 pub mod mpris;
+pub mod osc;
+#[cfg(feature = "pulsectl")]
+mod peak_meter;
 mod ui;
 
 const APP_NAME: &str = "Media Player Controller";
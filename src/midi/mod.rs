@@ -9,11 +9,9 @@ pub use msg::Msg;
 pub mod port;
 pub use port::{DirectionalPorts, PortsIn, PortsOut};
 
-pub mod sysex {
-    use super::Tag;
-    pub const TAG: Tag = Tag::from(0xf0);
-    pub const END_TAG: Tag = Tag::from(0xf7);
-}
+pub mod smpte;
+
+pub mod sysex;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Tag(u8);
@@ -190,15 +190,25 @@ pub enum ScannerStatus {
     Completed,
 }
 
+/// Sender for the messages received on a port pair, along with the index of the pair.
+pub type IndexedMsgSender = channel::Sender<(usize, Msg)>;
+
 pub struct InOutManager {
-    pub ins: PortsIn<channel::Sender<Msg>>,
+    pub ins: PortsIn<(usize, IndexedMsgSender)>,
     pub outs: PortsOut,
     state: State,
 }
 
 impl InOutManager {
-    pub fn try_new(client_name: Arc<str>, msg_tx: channel::Sender<Msg>) -> Result<Self, Error> {
-        let ins = PortsIn::try_new(client_name.clone(), msg_tx)?;
+    /// Creates a manager for the port pair at `index`.
+    ///
+    /// Messages received on the In port are sent along with `index`.
+    pub fn try_new(
+        client_name: Arc<str>,
+        index: usize,
+        msg_tx: IndexedMsgSender,
+    ) -> Result<Self, Error> {
+        let ins = PortsIn::try_new(client_name.clone(), (index, msg_tx))?;
         let outs = PortsOut::try_new(client_name)?;
 
         Ok(Self {
@@ -212,8 +222,8 @@ impl InOutManager {
         use Direction::*;
         match direction {
             In => {
-                self.ins.connect(port_name, |_ts, msg, msg_tx| {
-                    let _ = msg_tx.send(msg.into());
+                self.ins.connect(port_name, |_ts, msg, (index, msg_tx)| {
+                    let _ = msg_tx.send((*index, msg.into()));
                 })?;
             }
             Out => {
@@ -0,0 +1,267 @@
+use std::{fmt, time::Duration};
+
+/// SMPTE frame rate as used by MTC & MMC.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameRate {
+    Fps24,
+    Fps25,
+    /// 29.97 fps drop frame.
+    Fps29_97Df,
+    #[default]
+    Fps30,
+}
+
+impl FrameRate {
+    pub const ALL: [FrameRate; 4] = [
+        FrameRate::Fps24,
+        FrameRate::Fps25,
+        FrameRate::Fps29_97Df,
+        FrameRate::Fps30,
+    ];
+
+    /// Returns the 2 bits rate code, as found in the hours byte.
+    pub fn code(self) -> u8 {
+        use FrameRate::*;
+        match self {
+            Fps24 => 0,
+            Fps25 => 1,
+            Fps29_97Df => 2,
+            Fps30 => 3,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Self {
+        use FrameRate::*;
+        match code & 0x03 {
+            0 => Fps24,
+            1 => Fps25,
+            2 => Fps29_97Df,
+            _ => Fps30,
+        }
+    }
+
+    /// Number of frames per second in the timecode, i.e. 30 for drop frame.
+    pub fn frames(self) -> u8 {
+        use FrameRate::*;
+        match self {
+            Fps24 => 24,
+            Fps25 => 25,
+            Fps29_97Df | Fps30 => 30,
+        }
+    }
+
+    /// Actual number of frames per second.
+    pub fn fps(self) -> f64 {
+        let (num, den) = self.fps_ratio();
+        num as f64 / den as f64
+    }
+
+    /// Actual number of frames per second as a ratio,
+    /// so that conversions from & to durations are exact.
+    fn fps_ratio(self) -> (u128, u128) {
+        match self {
+            FrameRate::Fps29_97Df => (30_000, 1_001),
+            other => (other.frames() as u128, 1),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        use FrameRate::*;
+        match self {
+            Fps24 => "24 fps",
+            Fps25 => "25 fps",
+            Fps29_97Df => "29.97 fps drop frame",
+            Fps30 => "30 fps",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|rate| rate.name() == name)
+    }
+}
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// Frames dropped at the start of each minute, except every 10th minute.
+const DF_DROPPED: u32 = 2;
+/// Frames in 10 minutes of 29.97 df timecode.
+const DF_FRAMES_PER_10_MIN: u32 = 17_982;
+/// Frames in a minute of 29.97 df timecode, except every 10th minute.
+const DF_FRAMES_PER_MIN: u32 = 1_798;
+
+/// SMPTE time: hours, minutes, seconds & frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Time {
+    pub h: u8,
+    pub m: u8,
+    pub s: u8,
+    pub f: u8,
+    pub rate: FrameRate,
+}
+
+impl Time {
+    /// Builds the time for `dur`, wrapping after 24h.
+    pub fn from_duration(dur: Duration, rate: FrameRate) -> Self {
+        let (num, den) = rate.fps_ratio();
+        let frame_count = dur.as_nanos() * num / (den * NANOS_PER_SEC);
+        Self::from_frame_count(frame_count as u64, rate)
+    }
+
+    /// Builds the time for the frame at index `frame_count`, wrapping after 24h.
+    pub fn from_frame_count(frame_count: u64, rate: FrameRate) -> Self {
+        let mut frame_count = frame_count as u32;
+
+        if rate == FrameRate::Fps29_97Df {
+            // Add the dropped frame numbers back.
+            let tens = frame_count / DF_FRAMES_PER_10_MIN;
+            let rem = frame_count % DF_FRAMES_PER_10_MIN;
+            frame_count += 9 * DF_DROPPED * tens;
+            if rem >= DF_DROPPED {
+                frame_count += DF_DROPPED * ((rem - DF_DROPPED) / DF_FRAMES_PER_MIN);
+            }
+        }
+
+        let frames = rate.frames() as u32;
+        Self {
+            f: (frame_count % frames) as u8,
+            s: (frame_count / frames % 60) as u8,
+            m: (frame_count / (frames * 60) % 60) as u8,
+            h: (frame_count / (frames * 3_600) % 24) as u8,
+            rate,
+        }
+    }
+
+    pub fn to_duration(self) -> Duration {
+        let frames = self.rate.frames() as u32;
+        let mut frame_count =
+            ((self.h as u32 * 60 + self.m as u32) * 60 + self.s as u32) * frames + self.f as u32;
+
+        if self.rate == FrameRate::Fps29_97Df {
+            let minutes = self.h as u32 * 60 + self.m as u32;
+            frame_count -= DF_DROPPED * (minutes - minutes / 10);
+        }
+
+        // Round up so that `from_duration` gets back to the same frame.
+        let (num, den) = self.rate.fps_ratio();
+        let nanos = (frame_count as u128 * den * NANOS_PER_SEC).div_ceil(num);
+        Duration::from_nanos(nanos as u64)
+    }
+
+    /// Returns the hours byte, with the rate code in bits 5 & 6.
+    pub fn hours_byte(self) -> u8 {
+        (self.rate.code() << 5) | (self.h & 0x1f)
+    }
+
+    /// Builds the time from an hours byte with rate code & the other fields.
+    pub fn from_bytes(hours_byte: u8, m: u8, s: u8, f: u8) -> Self {
+        Self {
+            h: hours_byte & 0x1f,
+            m: m & 0x3f,
+            s: s & 0x3f,
+            f: f & 0x1f,
+            rate: FrameRate::from_code(hours_byte >> 5),
+        }
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sep = if self.rate == FrameRate::Fps29_97Df {
+            ';'
+        } else {
+            ':'
+        };
+
+        write!(
+            f,
+            "{:02}:{:02}:{:02}{sep}{:02}",
+            self.h, self.m, self.s, self.f
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn df(h: u8, m: u8, s: u8, f: u8) -> Time {
+        Time {
+            h,
+            m,
+            s,
+            f,
+            rate: FrameRate::Fps29_97Df,
+        }
+    }
+
+    #[test]
+    fn drop_frame_count() {
+        use FrameRate::Fps29_97Df;
+
+        for (frame_count, time) in [
+            (0, df(0, 0, 0, 0)),
+            (1_799, df(0, 0, 59, 29)),
+            // Frames 0 & 1 are dropped at the start of each minute...
+            (1_800, df(0, 1, 0, 2)),
+            (1_801, df(0, 1, 0, 3)),
+            (3_597, df(0, 1, 59, 29)),
+            (3_598, df(0, 2, 0, 2)),
+            (17_981, df(0, 9, 59, 29)),
+            // ... except every 10th minute.
+            (17_982, df(0, 10, 0, 0)),
+            (17_983, df(0, 10, 0, 1)),
+            (19_781, df(0, 10, 59, 29)),
+            (19_782, df(0, 11, 0, 2)),
+            (35_964, df(0, 20, 0, 0)),
+            (6 * 17_982, df(1, 0, 0, 0)),
+        ] {
+            assert_eq!(Time::from_frame_count(frame_count, Fps29_97Df), time);
+        }
+    }
+
+    #[test]
+    fn duration_round_trip() {
+        for rate in FrameRate::ALL {
+            // Covers the minute & 10 minutes boundaries.
+            let frame_counts = (0..40_000).chain(6 * 17_982 - 100..6 * 17_982 + 100);
+            for frame_count in frame_counts {
+                let time = Time::from_frame_count(frame_count, rate);
+                let dur = time.to_duration();
+                assert_eq!(
+                    Time::from_duration(dur, rate),
+                    time,
+                    "{frame_count} {dur:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn drop_frame_duration() {
+        let dur = df(0, 10, 0, 0).to_duration();
+        assert_eq!(
+            dur.as_nanos(),
+            (17_982 * 1_001_000_000_000u128).div_ceil(30_000)
+        );
+        assert_eq!(
+            Time::from_duration(Duration::from_secs(600), FrameRate::Fps29_97Df),
+            df(0, 10, 0, 0)
+        );
+        // Only 1 798.2 frames in a minute of actual time.
+        assert_eq!(
+            Time::from_duration(Duration::from_secs(60), FrameRate::Fps29_97Df),
+            df(0, 0, 59, 28)
+        );
+    }
+
+    #[test]
+    fn bytes() {
+        for rate in FrameRate::ALL {
+            let time = Time::from_frame_count(1_234_567, rate);
+            let time_bytes = Time::from_bytes(time.hours_byte(), time.m, time.s, time.f);
+            assert_eq!(time_bytes, time);
+        }
+
+        assert_eq!(df(23, 0, 0, 0).hours_byte(), 0x40 | 23);
+    }
+}
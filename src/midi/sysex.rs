@@ -0,0 +1,104 @@
+use super::{Msg, Tag};
+
+pub const TAG: Tag = Tag::from(0xf0);
+pub const END_TAG: Tag = Tag::from(0xf7);
+
+pub const UNIVERSAL_NON_REALTIME: u8 = 0x7e;
+pub const ALL_DEVICES: u8 = 0x7f;
+
+mod general_info {
+    pub const ID: u8 = 0x06;
+    pub const IDENTITY_REQUEST: u8 = 0x01;
+    pub const IDENTITY_REPLY: u8 = 0x02;
+}
+
+/// Device identity from a Universal Identity Reply.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Identity<'a> {
+    /// Device id, or channel, of the replying device.
+    pub device_id: u8,
+    /// Manufacturer id: 1 byte, or 3 bytes starting with 0.
+    pub manufacturer: &'a [u8],
+    /// Family, model & version, as defined by the manufacturer.
+    pub data: &'a [u8],
+}
+
+/// Builds a Universal Identity Request for all devices.
+pub fn identity_request() -> Msg {
+    use general_info::*;
+
+    Msg::new_sysex(&[UNIVERSAL_NON_REALTIME, ALL_DEVICES, ID, IDENTITY_REQUEST])
+}
+
+/// Parses the sysex `payload` as a Universal Identity Reply.
+///
+/// Returns `None` if `payload` is not an Identity Reply.
+pub fn parse_identity_reply(payload: &[u8]) -> Option<Identity<'_>> {
+    use general_info::*;
+
+    let (device_id, rem) = match payload {
+        [UNIVERSAL_NON_REALTIME, device_id, ID, IDENTITY_REPLY, rem @ ..] => (*device_id, rem),
+        _ => return None,
+    };
+
+    let manufacturer_len = match rem.first()? {
+        0x00 => 3,
+        _ => 1,
+    };
+    if rem.len() < manufacturer_len {
+        return None;
+    }
+    let (manufacturer, data) = rem.split_at(manufacturer_len);
+
+    Some(Identity {
+        device_id,
+        manufacturer,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_request() {
+        assert_eq!(
+            super::identity_request().inner(),
+            [0xf0, 0x7e, 0x7f, 0x06, 0x01, 0xf7]
+        );
+    }
+
+    #[test]
+    fn identity_reply() {
+        // 1 byte manufacturer id.
+        let payload = [0x7e, 0x03, 0x06, 0x02, 0x42, 0x13, 0x01, 0x00, 0x00];
+        assert_eq!(
+            parse_identity_reply(&payload),
+            Some(Identity {
+                device_id: 0x03,
+                manufacturer: &[0x42],
+                data: &[0x13, 0x01, 0x00, 0x00],
+            })
+        );
+
+        // 3 bytes manufacturer id.
+        let payload = [0x7e, 0x00, 0x06, 0x02, 0x00, 0x20, 0x32, 0x01];
+        assert_eq!(
+            parse_identity_reply(&payload),
+            Some(Identity {
+                device_id: 0x00,
+                manufacturer: &[0x00, 0x20, 0x32],
+                data: &[0x01],
+            })
+        );
+
+        assert_eq!(parse_identity_reply(&[0x7e, 0x00, 0x06, 0x02]), None);
+        assert_eq!(
+            parse_identity_reply(&[0x7e, 0x00, 0x06, 0x02, 0x00, 0x20]),
+            None
+        );
+        assert_eq!(parse_identity_reply(&[0x7e, 0x7f, 0x06, 0x01]), None);
+        assert_eq!(parse_identity_reply(&[0x42, 0x00, 0x06, 0x02, 0x42]), None);
+    }
+}
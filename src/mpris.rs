@@ -3,15 +3,19 @@ use crossbeam_channel as channel;
 #[cfg(feature = "pulsectl")]
 use pulsectl::controllers::{DeviceControl, SinkController};
 
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
 use crate::ctrl_surf::{self, CtrlSurfEvent};
 
 const PROGRESS_INTERVAL_MS: u32 = 250;
 const LOW_VOLUME: f64 = 0.1f64;
+pub const DEFAULT_SEEK_STEP: Duration = Duration::from_secs(1);
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -45,6 +49,8 @@ pub enum Event {
     PlayerSpawned(Arc<str>),
     Caps(Caps),
     Mixer(ctrl_surf::event::Mixer),
+    /// Mixer event for the player at the given index in the players list.
+    Strip(usize, ctrl_surf::event::Mixer),
     Data(ctrl_surf::event::Data),
     Transport(ctrl_surf::event::Transport),
 }
@@ -87,6 +93,9 @@ impl From<mpris::Event> for Event {
             Stopped | PlayerShutDown => Transport::Stop.into(),
             VolumeChanged(value) => Mixer::Volume(value).into(),
             TrackChanged(meta) => Data::from(Track::from(meta)).into(),
+            PlaybackRateChanged(rate) => Data::Rate(rate).into(),
+            ShuffleToggled(shuffle) => Data::Shuffle(shuffle).into(),
+            LoopingChanged(status) => Data::LoopStatus(status.into()).into(),
             _ => {
                 log::warn!("Player event {:?}", event);
                 Mixer::Mute.into()
@@ -110,10 +119,32 @@ impl From<mpris::PlaybackStatus> for Event {
 
 bitflags::bitflags! {
     pub struct Caps: u16 {
-        const SEEK     = 0b00000001;
-        const PREVIOUS = 0b00000010;
-        const NEXT     = 0b00000100;
-        const VOLUME   = 0b00001000;
+        const SEEK       = 0b00000001;
+        const PREVIOUS   = 0b00000010;
+        const NEXT       = 0b00000100;
+        const VOLUME     = 0b00001000;
+        const SHUFFLE    = 0b00010000;
+        const LOOP       = 0b00100000;
+    }
+}
+
+impl From<mpris::LoopStatus> for ctrl_surf::LoopStatus {
+    fn from(status: mpris::LoopStatus) -> Self {
+        match status {
+            mpris::LoopStatus::None => Self::None,
+            mpris::LoopStatus::Track => Self::Track,
+            mpris::LoopStatus::Playlist => Self::Playlist,
+        }
+    }
+}
+
+impl From<ctrl_surf::LoopStatus> for mpris::LoopStatus {
+    fn from(status: ctrl_surf::LoopStatus) -> Self {
+        match status {
+            ctrl_surf::LoopStatus::None => Self::None,
+            ctrl_surf::LoopStatus::Track => Self::Track,
+            ctrl_surf::LoopStatus::Playlist => Self::Playlist,
+        }
     }
 }
 
@@ -145,9 +176,21 @@ struct CurrentPlayer {
     caps: Caps,
 }
 
+/// A player from the list, controlled from a dedicated control surface strip.
+#[derive(Debug)]
+struct Strip {
+    player: mpris::Player,
+    volume: Volume,
+    /// Last volume sent for this strip.
+    last_vol: Option<f64>,
+}
+
 pub struct Players {
     list: Vec<Arc<str>>,
+    /// One strip per entry in `list`.
+    strips: Vec<Strip>,
     cur: Option<CurrentPlayer>,
+    seek_step: Duration,
     evt_tx: channel::Sender<Event>,
     #[cfg(feature = "pulsectl")]
     volume_controller: SinkController,
@@ -157,9 +200,13 @@ pub struct Players {
 
 // FIXME is this still needed now that mpris::Player no longer uses a lifetime.
 struct CurrentPlayerView<'a> {
+    #[cfg(feature = "pulsectl")]
+    name: &'a str,
     player: &'a mpris::Player,
     volume: &'a mut Volume,
     caps: &'a mut Caps,
+    seek_step: Duration,
+    index: Option<usize>,
 
     evt_tx: channel::Sender<Event>,
     #[cfg(feature = "pulsectl")]
@@ -183,7 +230,9 @@ impl Players {
         Ok((
             Self {
                 list: Vec::new(),
+                strips: Vec::new(),
                 cur: None,
+                seek_step: DEFAULT_SEEK_STEP,
                 evt_tx,
                 #[cfg(feature = "pulsectl")]
                 volume_controller,
@@ -196,7 +245,8 @@ impl Players {
 
     pub fn refresh(&mut self) -> Result<(), Error> {
         let finder = mpris::PlayerFinder::new()?;
-        self.list.clear();
+        let prev_list = std::mem::take(&mut self.list);
+        let prev_strips = std::mem::take(&mut self.strips);
 
         let mut cur_found = false;
         for player in finder.find_all()? {
@@ -205,10 +255,25 @@ impl Players {
                 cur_found |= cur.name == name;
             }
 
+            let volume = prev_list
+                .iter()
+                .zip(prev_strips.iter())
+                .find_map(|(prev_name, strip)| (*prev_name == name).then_some(strip.volume))
+                .unwrap_or_default();
+
             self.list.push(name);
+            self.strips.push(Strip {
+                player,
+                volume,
+                last_vol: None,
+            });
         }
 
+        self.send_strips_data()?;
+
         if cur_found {
+            // The current player might have moved in the list.
+            self.send_cur_index()?;
             return Ok(());
         }
 
@@ -245,9 +310,13 @@ impl Players {
 
     fn cur_view(&mut self) -> Option<CurrentPlayerView<'_>> {
         self.cur.as_mut().map(|cur| CurrentPlayerView {
+            #[cfg(feature = "pulsectl")]
+            name: &cur.name,
             player: &cur.player,
             volume: &mut cur.volume,
             caps: &mut cur.caps,
+            seek_step: self.seek_step,
+            index: self.list.iter().position(|name| *name == cur.name),
 
             evt_tx: self.evt_tx.clone(),
             #[cfg(feature = "pulsectl")]
@@ -261,6 +330,11 @@ impl Players {
         self.list.iter().cloned()
     }
 
+    /// Sets the position offset applied for each `StepForward` / `StepBackward` step.
+    pub fn set_seek_step(&mut self, seek_step: Duration) {
+        self.seek_step = seek_step;
+    }
+
     pub fn set_cur(&mut self, name: Arc<str>) -> Result<(), Error> {
         if !self.list.iter().any(|n| n == &name) {
             return Err(Error::Unknwon(name));
@@ -279,6 +353,8 @@ impl Players {
                 log::info!("Unmuting using system mixer");
                 self.volume_controller
                     .set_device_mute_by_index(self.device_index, false);
+                self.evt_tx
+                    .send(ctrl_surf::event::Data::SystemMuted(false).into())?;
                 self.evt_tx.send(ctrl_surf::event::Mixer::Unmute.into())?;
             }
         }
@@ -302,14 +378,27 @@ impl Players {
 
 impl Players {
     pub fn handle_event(&mut self, event: impl Into<CtrlSurfEvent>) -> Result<(), Error> {
-        if let Some(mut cur_view) = self.cur_view() {
-            cur_view.handle_event(event)?;
+        use CtrlSurfEvent::*;
+
+        match event.into() {
+            SelectPlayer(index) => match self.list.get(index).cloned() {
+                Some(name) => self.set_cur(name)?,
+                None => log::debug!("No player at index {index}"),
+            },
+            Strip(index, event) => self.handle_strip_event(index, event)?,
+            event => {
+                if let Some(mut cur_view) = self.cur_view() {
+                    cur_view.handle_event(event)?;
+                }
+            }
         }
 
         Ok(())
     }
 
     pub fn send_all_data(&mut self) -> Result<(), Error> {
+        self.send_strips_data()?;
+
         if let Some(mut cur_view) = self.cur_view() {
             cur_view.update_caps()?;
             cur_view.send_all_data()?;
@@ -326,6 +415,161 @@ impl Players {
         Ok(())
     }
 
+    fn handle_strip_event(
+        &mut self,
+        index: usize,
+        event: ctrl_surf::event::Mixer,
+    ) -> Result<(), Error> {
+        use ctrl_surf::event::Mixer;
+
+        let is_cur = self
+            .cur
+            .as_ref()
+            .is_some_and(|cur| self.list.get(index) == Some(&cur.name));
+        if is_cur {
+            // Current player also handles system mixer fallbacks.
+            if let Some(mut cur_view) = self.cur_view() {
+                cur_view.handle_event(event)?;
+            }
+
+            return Ok(());
+        }
+
+        let strip = match self.strips.get_mut(index) {
+            Some(strip) => strip,
+            None => {
+                log::debug!("No player for strip {index}");
+                return Ok(());
+            }
+        };
+
+        match event {
+            Mixer::Volume(vol) => {
+                if strip.player.checked_set_volume(vol)? {
+                    strip.volume = Volume::Unmuted;
+                    strip.last_vol = Some(vol);
+                    self.evt_tx.send(Event::Strip(index, Mixer::Volume(vol)))?;
+                }
+            }
+            Mixer::Mute => {
+                if !strip.volume.is_muted() {
+                    let vol = match strip.player.checked_get_volume()? {
+                        Some(vol) => vol,
+                        None => {
+                            log::debug!("Can't mute strip {index}: no volume");
+                            return Ok(());
+                        }
+                    };
+
+                    // Keep it low if already muted as we don't know previous volume.
+                    let prev_vol = if vol < f64::EPSILON { LOW_VOLUME } else { vol };
+                    strip.player.set_volume(0f64)?;
+                    strip.volume = Volume::Muted { prev_vol };
+                    strip.last_vol = Some(0f64);
+                }
+                self.evt_tx.send(Event::Strip(index, Mixer::Mute))?;
+            }
+            Mixer::Unmute => {
+                let vol = match strip.volume {
+                    Volume::Muted { prev_vol } => prev_vol,
+                    Volume::Unmuted | Volume::Unknown => match strip.player.checked_get_volume()? {
+                        Some(vol) if vol < f64::EPSILON => LOW_VOLUME,
+                        Some(vol) => vol,
+                        None => {
+                            log::debug!("Can't unmute strip {index}: no volume");
+                            return Ok(());
+                        }
+                    },
+                };
+
+                strip.player.set_volume(vol)?;
+                strip.volume = Volume::Unmuted;
+                strip.last_vol = Some(vol);
+                self.evt_tx.send(Event::Strip(index, Mixer::Unmute))?;
+                self.evt_tx.send(Event::Strip(index, Mixer::Volume(vol)))?;
+            }
+            Mixer::Balance(_) => log::debug!("Balance not available for strip {index}"),
+        }
+
+        Ok(())
+    }
+
+    /// Sends the players list and the volume of each player.
+    fn send_strips_data(&mut self) -> Result<(), Error> {
+        use ctrl_surf::event::Data;
+
+        self.evt_tx
+            .send(Data::PlayerList(self.list.clone()).into())?;
+
+        for index in 0..self.strips.len() {
+            self.send_strip_volume(index, true)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends the volume of the strips changed from the players themselves.
+    ///
+    /// Only the current player has an event loop, so the others must be polled.
+    pub fn poll_strips(&mut self) -> Result<(), Error> {
+        let cur_index = self
+            .cur
+            .as_ref()
+            .and_then(|cur| self.list.iter().position(|name| *name == cur.name));
+
+        for index in 0..self.strips.len() {
+            if Some(index) != cur_index {
+                self.send_strip_volume(index, false)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends the volume & mute state of the strip at `index`, if changed or `force`d.
+    fn send_strip_volume(&mut self, index: usize, force: bool) -> Result<(), Error> {
+        use ctrl_surf::event::Mixer;
+
+        let strip = &mut self.strips[index];
+        let vol = match strip.player.checked_get_volume() {
+            Ok(Some(vol)) => vol,
+            _ => return Ok(()),
+        };
+
+        if !force && matches!(strip.last_vol, Some(last) if (last - vol).abs() < f64::EPSILON) {
+            return Ok(());
+        }
+        strip.last_vol = Some(vol);
+
+        if vol > f64::EPSILON && strip.volume.is_muted() {
+            // Unmuted from the player.
+            strip.volume = Volume::Unmuted;
+        }
+
+        self.evt_tx.send(Event::Strip(index, Mixer::Volume(vol)))?;
+        let mute = if vol < f64::EPSILON || strip.volume.is_muted() {
+            Mixer::Mute
+        } else {
+            Mixer::Unmute
+        };
+        self.evt_tx.send(Event::Strip(index, mute))?;
+
+        Ok(())
+    }
+
+    fn send_cur_index(&self) -> Result<(), Error> {
+        let index = self
+            .cur
+            .as_ref()
+            .and_then(|cur| self.list.iter().position(|name| *name == cur.name));
+        if let Some(index) = index {
+            self.evt_tx
+                .send(ctrl_surf::event::Data::PlayerIndex(index).into())?;
+        }
+
+        Ok(())
+    }
+
     pub fn unmute_system(&mut self) {
         #[cfg(feature = "pulsectl")]
         {
@@ -354,13 +598,24 @@ impl<'a> CurrentPlayerView<'a> {
                     PlayPause => self.player.play_pause()?,
                     Previous => self.player.previous()?,
                     Next => self.player.next()?,
-                    StepForward => todo!(),
-                    StepBackward => todo!(),
+                    StepForward(steps) => self.step(steps as i64)?,
+                    StepBackward(steps) => self.step(-(steps as i64))?,
                     SetPosition(pos) => {
                         let cur_pos = self.player.get_position()?;
                         let target = pos.as_micros() as i64 - cur_pos.as_micros() as i64;
                         self.player.seek(target)?;
                     }
+                    SetRate(rate) => self.set_rate(rate)?,
+                    SetShuffle(shuffle) => {
+                        if !self.player.checked_set_shuffle(shuffle)? {
+                            log::debug!("Player can't shuffle");
+                        }
+                    }
+                    SetLoopStatus(status) => {
+                        if !self.player.checked_set_loop_status(status.into())? {
+                            log::debug!("Player can't loop");
+                        }
+                    }
                 }
             }
             Mixer(event) => {
@@ -369,20 +624,96 @@ impl<'a> CurrentPlayerView<'a> {
                     Volume(value) => self.player.set_volume(value)?,
                     Mute => self.mute()?,
                     Unmute => self.unmute()?,
+                    Balance(value) => self.set_balance(value)?,
                 }
             }
             DataRequest => {
                 self.update_caps()?;
                 self.send_all_data()?;
             }
+            Strip(..) | SelectPlayer(_) => {
+                log::warn!("Strip events must be handled by Players");
+            }
+            SelectPlayerSlot(_) => {
+                log::warn!("Player slot events must be handled by the controller");
+            }
+            Marker => {
+                log::warn!("Marker events must be handled by the controller");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn step(&self, steps: i64) -> Result<(), Error> {
+        if !self.caps.contains(Caps::SEEK) {
+            log::debug!("Player can't seek, ignoring {steps} step(s)");
+            return Ok(());
+        }
+
+        self.player
+            .seek(steps * self.seek_step.as_micros() as i64)?;
+
+        Ok(())
+    }
+
+    fn set_rate(&self, rate: f64) -> Result<(), Error> {
+        let range = self.player.get_valid_playback_rate_range()?;
+        let rate = rate.clamp(range.start, range.end);
+
+        if !self.player.checked_set_playback_rate(rate)? {
+            log::debug!("Player can't set playback rate");
+        }
+
+        Ok(())
+    }
+
+    /// Sets the balance of the player's sink input.
+    fn set_balance(&mut self, balance: f64) -> Result<(), Error> {
+        #[cfg(feature = "pulsectl")]
+        {
+            use pulsectl::controllers::AppControl;
+
+            let app = match crate::peak_meter::find_sink_input(self.volume_controller, self.name) {
+                Some((sink_input, _)) => self.volume_controller.get_app_by_index(sink_input)?,
+                None => {
+                    log::debug!("Can't set balance: no sink input for {}", self.name);
+                    return Ok(());
+                }
+            };
+
+            log::debug!("Setting balance {balance:.2} on sink input {}", app.index);
+            let mut volume = app.volume;
+            if volume
+                .set_balance(&app.channel_map, balance as f32)
+                .is_some()
+            {
+                let handler = &mut self.volume_controller.handler;
+                let op = handler
+                    .introspect
+                    .set_sink_input_volume(app.index, &volume, None);
+                if let Err(err) = handler.wait_for_operation(op) {
+                    log::warn!("Couldn't set balance {balance:.2}: {err}");
+                }
+            } else {
+                log::warn!("Couldn't set balance {balance:.2}");
+            }
         }
 
+        #[cfg(not(feature = "pulsectl"))]
+        log::debug!("Setting balance {balance:.2} not available");
+
         Ok(())
     }
 
-    fn send_all_data(&self) -> Result<(), Error> {
+    fn send_all_data(&mut self) -> Result<(), Error> {
         self.evt_tx.send(Event::Caps(*self.caps))?;
 
+        if let Some(index) = self.index {
+            self.evt_tx
+                .send(ctrl_surf::event::Data::PlayerIndex(index).into())?;
+        }
+
         self.evt_tx
             .send(self.player.get_playback_status()?.into())?;
 
@@ -391,6 +722,44 @@ impl<'a> CurrentPlayerView<'a> {
                 .send(ctrl_surf::event::Mixer::Volume(vol).into())?;
         }
 
+        if let Some(rate) = self.player.checked_get_playback_rate()? {
+            self.evt_tx
+                .send(ctrl_surf::event::Data::Rate(rate).into())?;
+        }
+
+        if let Some(shuffle) = self.player.checked_get_shuffle()? {
+            self.evt_tx
+                .send(ctrl_surf::event::Data::Shuffle(shuffle).into())?;
+        }
+
+        if let Some(status) = self.player.checked_get_loop_status()? {
+            self.evt_tx
+                .send(ctrl_surf::event::Data::LoopStatus(status.into()).into())?;
+        }
+
+        #[cfg(feature = "pulsectl")]
+        {
+            use pulsectl::controllers::AppControl;
+
+            let app = crate::peak_meter::find_sink_input(self.volume_controller, self.name)
+                .and_then(|(sink_input, _)| {
+                    self.volume_controller.get_app_by_index(sink_input).ok()
+                });
+            if let Some(app) = app {
+                let balance = app.volume.get_balance(&app.channel_map);
+                self.evt_tx
+                    .send(ctrl_surf::event::Mixer::Balance(balance as f64).into())?;
+            }
+
+            if let Ok(device) = self
+                .volume_controller
+                .get_device_by_index(self.device_index)
+            {
+                self.evt_tx
+                    .send(ctrl_surf::event::Data::SystemMuted(device.mute).into())?;
+            }
+        }
+
         self.send_track_meta()?;
 
         if let Ok(pos) = self.player.get_position() {
@@ -403,7 +772,8 @@ impl<'a> CurrentPlayerView<'a> {
 
     fn send_track_meta(&self) -> Result<(), Error> {
         if let Ok(meta) = self.player.get_metadata() {
-            self.evt_tx.send(ctrl_surf::Track::from(meta).into())?;
+            self.evt_tx
+                .send(track_from_meta(self.player, meta).into())?;
         }
 
         Ok(())
@@ -452,6 +822,8 @@ impl<'a> CurrentPlayerView<'a> {
             *self.volume = Muted {
                 prev_vol: LOW_VOLUME,
             };
+            self.evt_tx
+                .send(ctrl_surf::event::Data::SystemMuted(true).into())?;
             self.evt_tx.send(Mixer::Mute.into())?;
         }
 
@@ -501,6 +873,8 @@ impl<'a> CurrentPlayerView<'a> {
             self.volume_controller
                 .set_device_mute_by_index(self.device_index, false);
             *self.volume = Unmuted;
+            self.evt_tx
+                .send(ctrl_surf::event::Data::SystemMuted(false).into())?;
             self.evt_tx.send(Mixer::Unmute.into())?;
         }
 
@@ -525,6 +899,19 @@ impl Players {
             }
         });
 
+        #[cfg(feature = "pulsectl")]
+        {
+            let evt_tx = self.evt_tx.clone();
+            let must_stop_cl = must_stop.clone();
+            let name_cl = name.clone();
+            log::debug!("Spawning peak meter for MPRIS player {name}");
+            std::thread::spawn(move || {
+                if let Err(err) = crate::peak_meter::run(name_cl, evt_tx, must_stop_cl) {
+                    log::error!("Peak meter: {err}");
+                }
+            });
+        }
+
         let evt_tx = self.evt_tx.clone();
         let must_stop_cl = must_stop.clone();
         log::debug!("Spawning progress loop for MPRIS player {name}");
@@ -565,6 +952,9 @@ impl Players {
                 mpris::Event::VolumeChanged(_) => {
                     evt_tx.send(ctrl_surf::event::Mixer::Mute.into())?;
                 }
+                mpris::Event::TrackChanged(meta) => {
+                    evt_tx.send(track_from_meta(&player, meta).into())?;
+                }
                 event => evt_tx.send(event.into())?,
             }
         }
@@ -616,6 +1006,13 @@ fn get_caps(player: &mpris::Player) -> Result<Caps, Error> {
         caps.insert(Caps::NEXT);
     }
 
+    if player.can_shuffle()? {
+        caps.insert(Caps::SHUFFLE);
+    }
+    if player.can_loop()? {
+        caps.insert(Caps::LOOP);
+    }
+
     if let Some(vol) = player.checked_get_volume()? {
         // Try to set volume to same value to check if players supports it.
         if player.checked_set_volume(vol)? {
@@ -626,6 +1023,28 @@ fn get_caps(player: &mpris::Player) -> Result<Caps, Error> {
     Ok(caps)
 }
 
+/// Builds a `Track` with its position in the player's track list when available.
+fn track_from_meta(player: &mpris::Player, meta: mpris::Metadata) -> ctrl_surf::Track {
+    let list_index = if player.supports_track_lists() {
+        meta.track_id().and_then(|track_id| {
+            player
+                .get_track_list()
+                .map_err(|err| log::warn!("Failed to get track list: {err}"))
+                .ok()?
+                .ids()
+                .iter()
+                .position(|id| *id == track_id)
+        })
+    } else {
+        None
+    };
+
+    ctrl_surf::Track {
+        list_index,
+        ..ctrl_surf::Track::from(meta)
+    }
+}
+
 impl From<mpris::Metadata> for ctrl_surf::Track {
     fn from(meta: mpris::Metadata) -> Self {
         let artist = meta
@@ -634,10 +1053,18 @@ impl From<mpris::Metadata> for ctrl_surf::Track {
 
         let image_url = meta.art_url().map(Arc::from);
 
+        let id = meta
+            .url()
+            .map(Arc::from)
+            .or_else(|| meta.track_id().map(|id| Arc::from(id.as_str())));
+
         ctrl_surf::Track {
+            id,
             artist,
             album: meta.album_name().map(Arc::from),
             title: meta.title().map(Arc::from),
+            number: meta.track_number().and_then(|num| u32::try_from(num).ok()),
+            list_index: None,
             duration: meta.length(),
             image_url,
         }
@@ -0,0 +1,20 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Couldn't listen to OSC on UDP {}: {}", .0, .1)]
+    Bind(std::net::SocketAddr, std::io::Error),
+
+    #[error("OSC socket error: {}", .0)]
+    Socket(#[from] std::io::Error),
+
+    #[error("Truncated OSC packet")]
+    Truncated,
+
+    #[error("Invalid OSC string")]
+    InvalidString,
+
+    #[error("Invalid OSC address {}", .0)]
+    InvalidAddress(String),
+
+    #[error("Unsupported OSC type tag {}", .0)]
+    UnsupportedTypeTag(char),
+}
@@ -0,0 +1,8 @@
+mod error;
+pub use error::Error;
+
+pub mod msg;
+pub use msg::{Arg, Msg};
+
+pub mod socket;
+pub use socket::{MsgSender, Socket};
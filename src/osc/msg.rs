@@ -0,0 +1,359 @@
+use std::{fmt, sync::Arc};
+
+use super::Error;
+
+const BUNDLE_TAG: &[u8] = b"#bundle\0";
+/// Size of the time tag following the bundle tag.
+const TIME_TAG_LEN: usize = 8;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Arg {
+    Int(i32),
+    Float(f32),
+    Double(f64),
+    Str(Arc<str>),
+    Bool(bool),
+}
+
+impl Arg {
+    /// Returns the argument as a number, `true` being `1.0`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Arg::Int(val) => Some(val as f64),
+            Arg::Float(val) => Some(val as f64),
+            Arg::Double(val) => Some(val),
+            Arg::Bool(val) => Some(if val { 1.0 } else { 0.0 }),
+            Arg::Str(_) => None,
+        }
+    }
+
+    fn type_tag(&self) -> u8 {
+        match self {
+            Arg::Int(_) => b'i',
+            Arg::Float(_) => b'f',
+            Arg::Double(_) => b'd',
+            Arg::Str(_) => b's',
+            Arg::Bool(true) => b'T',
+            Arg::Bool(false) => b'F',
+        }
+    }
+}
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arg::Int(val) => write!(f, "{val}"),
+            Arg::Float(val) => write!(f, "{val}"),
+            Arg::Double(val) => write!(f, "{val}"),
+            Arg::Str(val) => write!(f, "{val:?}"),
+            Arg::Bool(val) => write!(f, "{val}"),
+        }
+    }
+}
+
+impl From<i32> for Arg {
+    fn from(val: i32) -> Self {
+        Arg::Int(val)
+    }
+}
+
+impl From<f32> for Arg {
+    fn from(val: f32) -> Self {
+        Arg::Float(val)
+    }
+}
+
+impl From<bool> for Arg {
+    fn from(val: bool) -> Self {
+        Arg::Bool(val)
+    }
+}
+
+impl From<Arc<str>> for Arg {
+    fn from(val: Arc<str>) -> Self {
+        Arg::Str(val)
+    }
+}
+
+impl From<&str> for Arg {
+    fn from(val: &str) -> Self {
+        Arg::Str(val.into())
+    }
+}
+
+/// An OSC message: an address pattern followed by arguments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Msg {
+    addr: Arc<str>,
+    args: Vec<Arg>,
+}
+
+impl Msg {
+    pub fn new(addr: &str) -> Self {
+        Self {
+            addr: addr.into(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn with(mut self, arg: impl Into<Arg>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    pub fn args(&self) -> &[Arg] {
+        &self.args
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+
+        push_str(&mut buf, self.addr.as_bytes());
+
+        let mut type_tags = vec![b','];
+        type_tags.extend(self.args.iter().map(Arg::type_tag));
+        push_str(&mut buf, &type_tags);
+
+        for arg in self.args.iter() {
+            match arg {
+                Arg::Int(val) => buf.extend(val.to_be_bytes()),
+                Arg::Float(val) => buf.extend(val.to_be_bytes()),
+                Arg::Double(val) => buf.extend(val.to_be_bytes()),
+                Arg::Str(val) => push_str(&mut buf, val.as_bytes()),
+                Arg::Bool(_) => (),
+            }
+        }
+
+        buf
+    }
+
+    /// Decodes the messages from an OSC packet.
+    ///
+    /// Bundles are flattened & their time tags are ignored.
+    pub fn decode_packet(buf: &[u8]) -> Result<Vec<Msg>, Error> {
+        let mut list = Vec::new();
+        Self::decode_into(buf, &mut list)?;
+
+        Ok(list)
+    }
+
+    fn decode_into(buf: &[u8], list: &mut Vec<Msg>) -> Result<(), Error> {
+        if buf.starts_with(BUNDLE_TAG) {
+            let mut reader = Reader(
+                buf.get(BUNDLE_TAG.len() + TIME_TAG_LEN..)
+                    .ok_or(Error::Truncated)?,
+            );
+            while !reader.0.is_empty() {
+                let len = reader.int()?;
+                let elem = reader.take(usize::try_from(len).map_err(|_| Error::Truncated)?)?;
+                Self::decode_into(elem, list)?;
+            }
+
+            return Ok(());
+        }
+
+        list.push(Self::decode(buf)?);
+
+        Ok(())
+    }
+
+    fn decode(buf: &[u8]) -> Result<Msg, Error> {
+        let mut reader = Reader(buf);
+
+        let addr = reader.str()?;
+        if !addr.starts_with('/') {
+            return Err(Error::InvalidAddress(addr.to_string()));
+        }
+
+        let mut msg = Msg::new(addr);
+        if reader.0.is_empty() {
+            // Type tags are optional in old implementations.
+            return Ok(msg);
+        }
+
+        let type_tags = reader.str()?;
+        let type_tags = type_tags.strip_prefix(',').ok_or(Error::InvalidString)?;
+        for type_tag in type_tags.chars() {
+            let arg = match type_tag {
+                'i' => Arg::Int(reader.int()?),
+                'f' => Arg::Float(f32::from_be_bytes(reader.word()?)),
+                'd' => {
+                    let mut bytes = [0u8; 8];
+                    bytes.copy_from_slice(reader.take(8)?);
+                    Arg::Double(f64::from_be_bytes(bytes))
+                }
+                's' => Arg::Str(reader.str()?.into()),
+                'T' => Arg::Bool(true),
+                'F' => Arg::Bool(false),
+                other => return Err(Error::UnsupportedTypeTag(other)),
+            };
+            msg.args.push(arg);
+        }
+
+        Ok(msg)
+    }
+}
+
+impl fmt::Display for Msg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.addr)?;
+        for arg in self.args.iter() {
+            write!(f, " {arg}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Pushes `bytes` as a null terminated string padded to 4 bytes.
+fn push_str(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend(bytes);
+    let padding = 4 - bytes.len() % 4;
+    buf.resize(buf.len() + padding, 0);
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(Error::Truncated);
+        }
+
+        let (taken, rem) = self.0.split_at(len);
+        self.0 = rem;
+
+        Ok(taken)
+    }
+
+    fn word(&mut self) -> Result<[u8; 4], Error> {
+        let mut word = [0u8; 4];
+        word.copy_from_slice(self.take(4)?);
+
+        Ok(word)
+    }
+
+    fn int(&mut self) -> Result<i32, Error> {
+        Ok(i32::from_be_bytes(self.word()?))
+    }
+
+    fn str(&mut self) -> Result<&'a str, Error> {
+        let len = self
+            .0
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(Error::Truncated)?;
+        let padded_len = (len / 4 + 1) * 4;
+        let bytes = self.take(padded_len.min(self.0.len()))?;
+
+        std::str::from_utf8(&bytes[..len]).map_err(|_| Error::InvalidString)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle(elems: &[Vec<u8>]) -> Vec<u8> {
+        let mut buf = BUNDLE_TAG.to_vec();
+        buf.extend([0, 0, 0, 0, 0, 0, 0, 1]);
+        for elem in elems {
+            buf.extend((elem.len() as i32).to_be_bytes());
+            buf.extend(elem);
+        }
+
+        buf
+    }
+
+    #[test]
+    fn round_trip() {
+        let msg = Msg::new("/transport/position")
+            .with(42)
+            .with(1.5f32)
+            .with("abc")
+            .with("abcd")
+            .with(true)
+            .with(false);
+        let mut msg_d = Msg::new("/d");
+        msg_d.args.push(Arg::Double(-0.25));
+
+        for msg in [Msg::new("/"), Msg::new("/abc"), msg, msg_d] {
+            let buf = msg.encode();
+            assert_eq!(buf.len() % 4, 0, "{msg}");
+            assert_eq!(Msg::decode_packet(&buf).unwrap(), [msg]);
+        }
+    }
+
+    #[test]
+    fn padding() {
+        // Strings are always null terminated, even when their length is a multiple of 4.
+        assert_eq!(Msg::new("/abc").encode(), b"/abc\0\0\0\0,\0\0\0");
+        assert_eq!(Msg::new("/ab").with(1).encode(), b"/ab\0,i\0\0\0\0\0\x01");
+
+        // Missing type tags & trailing padding are tolerated.
+        assert_eq!(
+            Msg::decode_packet(b"/abc\0\0\0\0").unwrap(),
+            [Msg::new("/abc")]
+        );
+        assert_eq!(Msg::decode_packet(b"/abc\0").unwrap(), [Msg::new("/abc")]);
+    }
+
+    #[test]
+    fn bundles() {
+        let msg1 = Msg::new("/transport/play");
+        let msg2 = Msg::new("/mixer/volume").with(0.5f32);
+        let msg3 = Msg::new("/transport/stop");
+
+        let nested = bundle(&[msg2.encode(), msg3.encode()]);
+        let buf = bundle(&[msg1.encode(), nested]);
+        assert_eq!(Msg::decode_packet(&buf).unwrap(), [msg1, msg2, msg3]);
+
+        assert!(Msg::decode_packet(&bundle(&[])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn truncated() {
+        let buf = Msg::new("/mixer/volume").with(0.5f32).with("abc").encode();
+        // The address alone is valid from its null terminator on.
+        let addr_len = "/mixer/volume".len();
+        for len in (0..=addr_len).chain(addr_len + 4..buf.len()) {
+            assert!(Msg::decode_packet(&buf[..len]).is_err(), "{len}");
+        }
+
+        // Bundle without time tag.
+        assert!(matches!(
+            Msg::decode_packet(BUNDLE_TAG),
+            Err(Error::Truncated)
+        ));
+
+        // Element longer than the bundle.
+        let mut buf = bundle(&[Msg::new("/abc").encode()]);
+        buf.truncate(buf.len() - 4);
+        assert!(matches!(Msg::decode_packet(&buf), Err(Error::Truncated)));
+
+        // Negative element length.
+        let mut buf = bundle(&[]);
+        buf.extend((-4i32).to_be_bytes());
+        assert!(matches!(Msg::decode_packet(&buf), Err(Error::Truncated)));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(matches!(
+            Msg::decode_packet(b"abc\0"),
+            Err(Error::InvalidAddress(_))
+        ));
+        assert!(matches!(
+            Msg::decode_packet(b"/abc\0\0\0\0i\0\0\0"),
+            Err(Error::InvalidString)
+        ));
+        assert!(matches!(
+            Msg::decode_packet(b"/abc\0\0\0\0,x\0\0"),
+            Err(Error::UnsupportedTypeTag('x'))
+        ));
+    }
+}
@@ -0,0 +1,117 @@
+use crossbeam_channel as channel;
+use std::{
+    net::{SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use super::{Error, Msg};
+
+/// Max size of a UDP datagram.
+const MAX_PACKET_SIZE: usize = 65_536;
+/// Interval at which the receiving thread checks whether it must stop.
+const RECV_TIMEOUT: Duration = Duration::from_millis(200);
+/// Delay before receiving again after a first socket error, doubled on each new error.
+const MIN_ERR_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_ERR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Sender for the messages received from OSC clients.
+pub type MsgSender = channel::Sender<(SocketAddr, Msg)>;
+
+/// UDP socket listening to OSC clients.
+///
+/// Received messages are forwarded to the `MsgSender` from a dedicated thread,
+/// which is stopped when the `Socket` is dropped.
+pub struct Socket {
+    socket: UdpSocket,
+    addr: SocketAddr,
+    is_running: Arc<AtomicBool>,
+    recv_thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Socket {
+    pub fn bind(addr: SocketAddr, msg_tx: MsgSender) -> Result<Self, Error> {
+        let socket = UdpSocket::bind(addr).map_err(|err| Error::Bind(addr, err))?;
+
+        let recv_socket = socket.try_clone()?;
+        recv_socket.set_read_timeout(Some(RECV_TIMEOUT))?;
+
+        let is_running = Arc::new(AtomicBool::new(true));
+        let recv_thread = std::thread::spawn({
+            let is_running = is_running.clone();
+            move || Self::recv_loop(recv_socket, is_running, msg_tx)
+        });
+
+        log::info!("Listening to OSC on UDP {addr}");
+
+        Ok(Self {
+            socket,
+            addr,
+            is_running,
+            recv_thread: Some(recv_thread),
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn send(&self, client: SocketAddr, msg: &Msg) -> Result<(), Error> {
+        self.socket.send_to(&msg.encode(), client).map_err(|err| {
+            log::error!("Failed to send OSC msg {msg} to {client}: {err}");
+            err
+        })?;
+
+        Ok(())
+    }
+
+    fn recv_loop(socket: UdpSocket, is_running: Arc<AtomicBool>, msg_tx: MsgSender) {
+        use std::io::ErrorKind::*;
+
+        let mut buf = vec![0u8; MAX_PACKET_SIZE];
+        let mut err_backoff = Duration::ZERO;
+        while is_running.load(Ordering::Relaxed) {
+            let (len, client) = match socket.recv_from(&mut buf) {
+                Ok(len_client) => len_client,
+                Err(err) if matches!(err.kind(), WouldBlock | TimedOut | Interrupted) => continue,
+                Err(err) => {
+                    // Can happen on some platforms after sending to a closed client port.
+                    // Don't spin if the error persists.
+                    err_backoff = (err_backoff * 2).clamp(MIN_ERR_BACKOFF, MAX_ERR_BACKOFF);
+                    log::debug!("OSC socket: {err}, retrying in {err_backoff:?}");
+                    std::thread::sleep(err_backoff);
+                    continue;
+                }
+            };
+            err_backoff = Duration::ZERO;
+
+            let list = match Msg::decode_packet(&buf[..len]) {
+                Ok(list) => list,
+                Err(err) => {
+                    log::warn!("Ignoring OSC packet from {client}: {err}");
+                    continue;
+                }
+            };
+
+            for msg in list {
+                if msg_tx.send((client, msg)).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        self.is_running.store(false, Ordering::Relaxed);
+        if let Some(recv_thread) = self.recv_thread.take() {
+            let _ = recv_thread.join();
+        }
+
+        log::info!("Stopped listening to OSC on UDP {}", self.addr);
+    }
+}
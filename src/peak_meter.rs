@@ -0,0 +1,271 @@
+//! Audio peak level meter.
+//!
+//! Monitors the sink input used by a player, or the default sink if the player's
+//! sink input can't be found, using a PulseAudio peak-detect record stream.
+//!
+//! This can be tested without a player by routing audio to a null sink:
+//!
+//! ```shell
+//! pactl load-module module-null-sink sink_name=meter_test
+//! pactl set-default-sink meter_test
+//! ```
+
+use crossbeam_channel as channel;
+use pulse::{
+    context::{self, Context},
+    def::BufferAttr,
+    mainloop::standard::{IterateResult, Mainloop},
+    sample,
+    stream::{self, PeekResult, Stream},
+};
+use pulsectl::controllers::{
+    types::{ApplicationInfo, DeviceInfo},
+    AppControl, DeviceControl, SinkController,
+};
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{ctrl_surf, mpris::Event};
+
+/// Number of peak values per second.
+const RATE: u32 = 25;
+/// Level decay factor applied for each peak value.
+const DECAY: f64 = 0.85;
+/// Levels below this are considered silence and only forwarded once.
+const SILENCE: f64 = 0.001;
+/// Wait duration when the main loop has nothing to dispatch.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Interval between attempts to find the player's sink input.
+const SINK_INPUT_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("PulseAudio error: {}", .0)]
+    Pulse(#[from] pulse::error::PAErr),
+
+    #[error("Volume controller error")]
+    Controller(#[from] pulsectl::ControllerError),
+
+    #[error("Peak meter init failed")]
+    Init,
+
+    #[error("Peak meter connection failed")]
+    Connection,
+
+    #[error("No monitor source for sink {}", .0)]
+    NoMonitor(u32),
+
+    #[error("Peak meter sending: {}", .0)]
+    EventSend(#[from] channel::SendError<Event>),
+}
+
+/// Runs the peak meter loop for `player_name` until `stopper` is set.
+///
+/// The player's sink input is looked up again when it disappears, or
+/// periodically while the default sink is monitored instead.
+pub fn run(
+    player_name: Arc<str>,
+    evt_tx: channel::Sender<Event>,
+    stopper: Arc<AtomicBool>,
+) -> Result<(), Error> {
+    let mut controller = SinkController::create()?;
+
+    let mut mainloop = Mainloop::new().ok_or(Error::Init)?;
+    let mut context =
+        Context::new(&mainloop, "media-player-controller peak meter").ok_or(Error::Init)?;
+    context.connect(None, context::FlagSet::NOFLAGS, None)?;
+
+    loop {
+        iterate(&mut mainloop)?;
+        match context.get_state() {
+            context::State::Ready => break,
+            context::State::Failed | context::State::Terminated => {
+                return Err(Error::Connection);
+            }
+            _ => (),
+        }
+    }
+
+    while !stopper.load(Ordering::Acquire) {
+        let sink_input = find_sink_input(&mut controller, &player_name);
+        let mut stream = match sink_input {
+            Some((sink_input, sink)) => {
+                log::debug!("Peak meter monitoring sink input {sink_input} for {player_name}");
+                let sink = controller.get_device_by_index(sink)?;
+                connect_stream(&mut mainloop, &mut context, sink, Some(sink_input))?
+            }
+            None => {
+                let sink = controller.get_default_device()?;
+                log::debug!(
+                    "Peak meter monitoring {:?} for {player_name}",
+                    sink.monitor_name,
+                );
+                connect_stream(&mut mainloop, &mut context, sink, None)?
+            }
+        };
+
+        let res = monitor(&mut mainloop, &mut stream, &evt_tx, &stopper, || {
+            // Look for the player's sink input if it wasn't found yet.
+            sink_input.is_none() && find_sink_input(&mut controller, &player_name).is_some()
+        });
+        let _ = stream.disconnect();
+        res?;
+    }
+
+    context.disconnect();
+
+    Ok(())
+}
+
+fn connect_stream(
+    mainloop: &mut Mainloop,
+    context: &mut Context,
+    sink: DeviceInfo,
+    sink_input: Option<u32>,
+) -> Result<Stream, Error> {
+    let monitor = sink.monitor_name.ok_or(Error::NoMonitor(sink.index))?;
+
+    let spec = sample::Spec {
+        format: sample::Format::F32le,
+        channels: 1,
+        rate: RATE,
+    };
+    let mut stream = Stream::new(context, "peak meter", &spec, None).ok_or(Error::Init)?;
+
+    if let Some(sink_input) = sink_input {
+        stream.set_monitor_stream(sink_input)?;
+    }
+
+    let attr = BufferAttr {
+        maxlength: u32::MAX,
+        tlength: u32::MAX,
+        prebuf: u32::MAX,
+        minreq: u32::MAX,
+        fragsize: std::mem::size_of::<f32>() as u32,
+    };
+    stream.connect_record(
+        Some(&monitor),
+        Some(&attr),
+        stream::FlagSet::PEAK_DETECT | stream::FlagSet::ADJUST_LATENCY,
+    )?;
+
+    loop {
+        iterate(mainloop)?;
+        match stream.get_state() {
+            stream::State::Ready => break,
+            stream::State::Failed | stream::State::Terminated => {
+                return Err(Error::Connection);
+            }
+            _ => (),
+        }
+    }
+
+    Ok(stream)
+}
+
+/// Forwards the levels from `stream` until `stopper` is set, the stream
+/// ends or `must_reconnect` returns `true`.
+fn monitor(
+    mainloop: &mut Mainloop,
+    stream: &mut Stream,
+    evt_tx: &channel::Sender<Event>,
+    stopper: &AtomicBool,
+    mut must_reconnect: impl FnMut() -> bool,
+) -> Result<(), Error> {
+    let mut level = 0f64;
+    let mut last_level = 1f64;
+    let mut last_check = Instant::now();
+    while !stopper.load(Ordering::Acquire) {
+        iterate(mainloop)?;
+
+        if !matches!(stream.get_state(), stream::State::Ready) {
+            log::debug!("Peak meter stream ended");
+            return Ok(());
+        }
+
+        if last_check.elapsed() >= SINK_INPUT_RETRY_INTERVAL {
+            if must_reconnect() {
+                return Ok(());
+            }
+            last_check = Instant::now();
+        }
+
+        let peak = match stream.peek()? {
+            PeekResult::Empty => continue,
+            PeekResult::Hole(_) => {
+                stream.discard()?;
+                continue;
+            }
+            PeekResult::Data(buf) => buf
+                .chunks_exact(std::mem::size_of::<f32>())
+                .last()
+                .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()) as f64),
+        };
+        stream.discard()?;
+
+        let peak = match peak {
+            Some(peak) => peak.abs(),
+            None => continue,
+        };
+
+        level = peak.max(level * DECAY);
+        // Devices decay their meters on their own, so keep sending while not silent.
+        if level > SILENCE || last_level > SILENCE {
+            evt_tx.send(ctrl_surf::event::Data::Level(level).into())?;
+            last_level = level;
+        }
+    }
+
+    Ok(())
+}
+
+/// Dispatches pending events without blocking, waits a bit if there were none.
+fn iterate(mainloop: &mut Mainloop) -> Result<(), Error> {
+    match mainloop.iterate(false) {
+        IterateResult::Success(0) => {
+            std::thread::sleep(POLL_INTERVAL);
+            Ok(())
+        }
+        IterateResult::Success(_) => Ok(()),
+        IterateResult::Quit(_) => Err(Error::Connection),
+        IterateResult::Err(err) => Err(err.into()),
+    }
+}
+
+/// Returns the sink input & sink indices used by `player_name`, if any.
+///
+/// Exact matches on the application name or binary are preferred
+/// over partial matches on the application name.
+pub fn find_sink_input(controller: &mut SinkController, player_name: &str) -> Option<(u32, u32)> {
+    let player_name = player_name.to_lowercase();
+
+    let apps = controller
+        .list_applications()
+        .map_err(|err| log::warn!("Couldn't list sink inputs: {err}"))
+        .ok()?;
+
+    let prop =
+        |app: &ApplicationInfo, key: &str| app.proplist.get_str(key).map(|val| val.to_lowercase());
+
+    let exact = apps.iter().find(|app| {
+        ["application.name", "application.process.binary"]
+            .iter()
+            .any(|key| prop(app, key).as_deref() == Some(player_name.as_str()))
+    });
+    let app = exact.or_else(|| {
+        apps.iter().find(|app| match prop(app, "application.name") {
+            Some(app_name) if !app_name.is_empty() => {
+                app_name.contains(&player_name) || player_name.contains(&app_name)
+            }
+            _ => false,
+        })
+    })?;
+
+    Some((app.index, app.connection_id))
+}
@@ -6,15 +6,21 @@ use super::{controller, Dispatcher};
 use crate::{ctrl_surf, midi};
 
 pub enum Request {
-    ConnectPort((midi::port::Direction, Arc<str>)),
-    DisconnectPort(midi::port::Direction),
+    ConnectPort((usize, midi::port::Direction, Arc<str>)),
+    DisconnectPort((usize, midi::port::Direction)),
     RefreshPorts,
     UseControlSurface(Arc<str>),
     NoControlSurface,
-    ResetControlSurface,
+    DisconnectControlSurface,
     ScanControlSurface,
+    FaderCurve(ctrl_surf::FaderCurve),
+    MidiLearn(Option<ctrl_surf::mapping::Action>),
+    OscAddr(std::net::SocketAddr),
+    MtcFrameRate(midi::smpte::FrameRate),
     UsePlayer(Arc<str>),
     RefreshPlayers,
+    PlayerSlot(Option<usize>),
+    SeekStep(std::time::Duration),
     Shutdown,
     Mixer(ctrl_surf::event::Mixer),
     Transport(ctrl_surf::event::Transport),
@@ -49,7 +55,7 @@ impl App {
         let (err_tx, err_rx) = channel::unbounded();
         let (req_tx, req_rx) = channel::unbounded();
 
-        let ctrl_surf_panel = Arc::new(Mutex::new(super::ControlSurfacePanel::new()));
+        let ctrl_surf_panel = Arc::new(Mutex::new(super::ControlSurfacePanel::new(cc.storage)));
         let ports_panel = Arc::new(Mutex::new(super::PortsPanel::new()));
         let player_panel = Arc::new(Mutex::new(super::PlayerPanel::new(cc)));
 
@@ -75,15 +81,24 @@ impl App {
         };
 
         this.send_req(Request::RefreshPorts);
-        for evt in super::PortsPanel::setup(cc.storage) {
+        // Additional port pairs depend on the Control Surface, so connect them last.
+        let (main_ports, aux_ports): (Vec<_>, Vec<_>) = super::PortsPanel::setup(cc.storage)
+            .partition(|resp| matches!(resp, super::port::Response::Connect((0, ..))));
+        for evt in main_ports {
             Dispatcher::<super::PortsPanel>::handle(&mut this, Some(evt));
         }
         if let Some(resp) = super::ControlSurfacePanel::setup(cc.storage) {
             Dispatcher::<super::ControlSurfacePanel>::handle(&mut this, Some(resp));
         }
-        if let Some(resp) = super::PlayerPanel::setup(cc.storage) {
+        for evt in aux_ports {
+            Dispatcher::<super::PortsPanel>::handle(&mut this, Some(evt));
+        }
+        let mut has_player = false;
+        for resp in super::PlayerPanel::setup(cc.storage) {
+            has_player |= matches!(resp, super::player::Response::Use(_));
             Dispatcher::<super::PlayerPanel>::handle(&mut this, Some(resp));
-        } else {
+        }
+        if !has_player {
             this.send_req(Request::RefreshPlayers);
         }
 
@@ -103,15 +118,31 @@ impl eframe::App for App {
 
             ui.add_space(2f32);
 
-            ui.horizontal(|ui| {
-                use midi::port::Direction;
+            let pairs = self.ports_panel.lock().unwrap().pairs();
+            for pair in 0..pairs {
+                ui.horizontal(|ui| {
+                    use midi::port::Direction;
 
-                let resp_in = self.ports_panel.lock().unwrap().show(Direction::In, ui);
-                ui.add_space(20f32);
-                let resp_out = self.ports_panel.lock().unwrap().show(Direction::Out, ui);
+                    if pairs > 1 {
+                        ui.label(format!("Device {}", pair + 1));
+                        ui.add_space(10f32);
+                    }
 
-                Dispatcher::<super::PortsPanel>::handle(self, resp_in.or(resp_out));
-            });
+                    let resp_in = self
+                        .ports_panel
+                        .lock()
+                        .unwrap()
+                        .show(pair, Direction::In, ui);
+                    ui.add_space(20f32);
+                    let resp_out = self
+                        .ports_panel
+                        .lock()
+                        .unwrap()
+                        .show(pair, Direction::Out, ui);
+
+                    Dispatcher::<super::PortsPanel>::handle(self, resp_in.or(resp_out));
+                });
+            }
 
             ui.add_space(2f32);
         });
@@ -149,7 +180,7 @@ impl eframe::App for App {
 
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
         log::info!("Exiting...");
-        self.send_req(Request::ResetControlSurface);
+        self.send_req(Request::DisconnectControlSurface);
     }
 }
 
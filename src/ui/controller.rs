@@ -1,6 +1,7 @@
 use crossbeam_channel as channel;
 use eframe::egui;
 use std::{
+    net::SocketAddr,
     ops::ControlFlow,
     sync::{Arc, Mutex},
     time::Duration,
@@ -9,11 +10,14 @@ use std::{
 use super::app;
 use crate::{
     ctrl_surf::{self, AppEvent},
-    midi, mpris,
+    midi, mpris, osc,
 };
 
 const CTRL_SURF_CONNECTION_TIMEOUT: Duration = Duration::from_millis(250);
+const CTRL_SURF_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(2);
+const CTRL_SURF_RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
 const TRACK_META_RETRY_DELAY: Duration = Duration::from_millis(250);
+const STRIPS_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 pub struct Spawner {
     pub req_rx: channel::Receiver<app::Request>,
@@ -46,6 +50,9 @@ pub enum Error {
     #[error("Couldn't connect to Control Surface: {}", .0)]
     ControlSurfaceConnection(Arc<str>),
 
+    #[error("Lost connection to Control Surface: {}", .0)]
+    ControlSurfaceLost(Arc<str>),
+
     #[error("Control Surface not found: {}", .0)]
     ControlSurfaceNotFound(Arc<str>),
 
@@ -56,7 +63,11 @@ pub enum Error {
 #[derive(Clone, Copy, Debug)]
 enum DelayedEvent {
     CtrlSurfConnectionTimeout,
+    CtrlSurfTick,
+    CtrlSurfKeepalive,
+    CtrlSurfReconnect,
     TrackMetaRetry,
+    StripsPoll,
 }
 
 struct Controller {
@@ -68,13 +79,33 @@ struct Controller {
     ctrl_surf: Option<ctrl_surf::ControlSurfaceArc>,
     ctrl_surf_panel: Arc<Mutex<super::ControlSurfacePanel>>,
     ctrl_surf_conn_timeout: Option<timer::Guard>,
-
+    ctrl_surf_tick: Option<timer::Guard>,
+    /// Next keepalive, or next reconnection attempt once the connection is lost.
+    ctrl_surf_keepalive: Option<timer::Guard>,
+    is_ctrl_surf_lost: bool,
+    /// Action to bind to the next MIDI message received from the device.
+    midi_learn: Option<ctrl_surf::mapping::Action>,
+
+    client_name: Arc<str>,
+    midi_tx: midi::port::IndexedMsgSender,
     midi_ports: midi::port::InOutManager,
+    /// Port pairs for the additional devices of a composite Control Surface.
+    aux_ports: Vec<midi::port::InOutManager>,
     ports_panel: Arc<Mutex<super::PortsPanel>>,
 
+    osc_tx: osc::MsgSender,
+    /// Socket for the OSC clients, when the Control Surface uses OSC.
+    osc_socket: Option<osc::Socket>,
+
     players: mpris::Players,
     player_panel: Arc<Mutex<super::PlayerPanel>>,
     player_meta_retry: Option<timer::Guard>,
+    strips_poll: Option<timer::Guard>,
+    track_id: Option<Arc<str>>,
+    position: Duration,
+    markers: ctrl_surf::Markers,
+    /// Whether the A-B loop sought back to A & the position wasn't updated yet.
+    is_ab_seeking: bool,
 
     must_repaint: bool,
     egui_ctx: egui::Context,
@@ -97,13 +128,15 @@ impl Controller {
         let (delayed_evt_tx, delayed_evt_rx) = channel::unbounded();
 
         let (midi_tx, midi_rx) = channel::unbounded();
-        let midi_ports = midi::port::InOutManager::try_new(client_name, midi_tx)
+        let midi_ports = midi::port::InOutManager::try_new(client_name.clone(), 0, midi_tx.clone())
             .context("Failed to create MIDI ports manager")
             .map_err(|err| {
                 log::error!("{err}");
                 let _ = err_tx.send(err);
             })?;
 
+        let (osc_tx, osc_rx) = channel::unbounded();
+
         let (players, evt_rx) = mpris::Players::try_new()
             .context("Failed to create MPRIS players manager")
             .map_err(|err| {
@@ -120,18 +153,34 @@ impl Controller {
             ctrl_surf: None,
             ctrl_surf_panel,
             ctrl_surf_conn_timeout: None,
+            ctrl_surf_tick: None,
+            ctrl_surf_keepalive: None,
+            is_ctrl_surf_lost: false,
+            midi_learn: None,
 
+            client_name,
+            midi_tx,
             midi_ports,
+            aux_ports: Vec::new(),
 
             ports_panel,
+
+            osc_tx,
+            osc_socket: None,
+
             players,
             player_panel,
             player_meta_retry: None,
+            strips_poll: None,
+            track_id: None,
+            position: Duration::ZERO,
+            markers: ctrl_surf::Markers::default(),
+            is_ab_seeking: false,
 
             must_repaint: false,
             egui_ctx,
         }
-        .run_loop(req_rx, evt_rx, midi_rx, delayed_evt_rx);
+        .run_loop(req_rx, evt_rx, midi_rx, osc_rx, delayed_evt_rx);
 
         Ok(())
     }
@@ -159,29 +208,66 @@ impl Controller {
         use app::Request::*;
 
         match request {
-            ConnectPort((direction, port_name)) => {
-                self.midi_ports.connect(direction, port_name)?;
+            ConnectPort((pair, direction, port_name)) => {
+                match self.ports_mut(pair) {
+                    Some(ports) => ports.connect(direction, port_name)?,
+                    None => {
+                        log::debug!("Ignoring port {port_name} for unused port pair {pair}");
+                        return Ok(ControlFlow::Continue(()));
+                    }
+                }
                 self.try_connect_ctrl_surf()?;
                 self.refresh_ports()?;
             }
-            DisconnectPort(direction) => {
-                self.send_to_ctrl_surf(ctrl_surf::event::Transport::Stop);
-                self.midi_ports.disconnect(direction)?;
+            DisconnectPort((pair, direction)) => {
+                if !self.ctrl_surf_uses_osc() {
+                    self.disconnect_ctrl_surf();
+                }
+                if let Some(ports) = self.ports_mut(pair) {
+                    ports.disconnect(direction)?;
+                }
                 self.refresh_ports()?;
             }
             RefreshPorts => self.refresh_ports()?,
             UseControlSurface(ctrl_surf) => self.use_ctrl_surf(ctrl_surf)?,
             NoControlSurface => {
-                self.send_to_ctrl_surf(ctrl_surf::event::Transport::Stop);
+                self.disconnect_ctrl_surf();
                 self.ctrl_surf = None;
+                self.ctrl_surf_tick = None;
+                self.midi_learn = None;
+                self.osc_socket = None;
+                self.aux_ports.clear();
+                self.update_ports_panel();
                 log::info!("Control Surface not used");
             }
-            ResetControlSurface => {
-                self.send_to_ctrl_surf(ctrl_surf::event::Transport::Stop);
-            }
+            DisconnectControlSurface => self.disconnect_ctrl_surf(),
             ScanControlSurface => self.start_scan(),
+            FaderCurve(curve) => self.set_fader_curve(curve)?,
+            MtcFrameRate(rate) => self.set_frame_rate(rate)?,
+            MidiLearn(action) => {
+                match action {
+                    Some(action) => log::info!("MIDI learn for {}", action.name()),
+                    None => log::info!("MIDI learn cancelled"),
+                }
+                self.midi_learn = action;
+            }
+            OscAddr(addr) => {
+                log::info!("OSC address {addr}");
+                if self.ctrl_surf_uses_osc() {
+                    let ctrl_surf_name = self.ctrl_surf_panel.lock().unwrap().cur.clone();
+                    self.use_ctrl_surf(ctrl_surf_name)?;
+                }
+            }
             UsePlayer(player_name) => self.players.set_cur(player_name)?,
             RefreshPlayers => self.refresh_players()?,
+            PlayerSlot(slot) => {
+                self.send_to_ctrl_surf(ctrl_surf::event::Data::PlayerSlot(slot));
+            }
+            SeekStep(seek_step) => {
+                self.players.set_seek_step(seek_step);
+                self.player_panel.lock().unwrap().set_seek_step(seek_step);
+                self.must_repaint = true;
+            }
             Shutdown => return Ok(ControlFlow::Break(())),
             Mixer(mevt) => {
                 log::debug!("UI Player: {mevt:?}");
@@ -205,15 +291,67 @@ impl Controller {
 impl Controller {
     fn refresh_ports(&mut self) -> anyhow::Result<()> {
         self.midi_ports.refresh()?;
-        self.ports_panel.lock().unwrap().update(&self.midi_ports);
+        for aux_ports in self.aux_ports.iter_mut() {
+            aux_ports.refresh()?;
+        }
+        self.update_ports_panel();
 
         Ok(())
     }
 
-    fn handle_midi_msg(&mut self, msg: midi::Msg) -> anyhow::Result<()> {
+    fn update_ports_panel(&self) {
+        self.ports_panel
+            .lock()
+            .unwrap()
+            .update(&self.midi_ports, &self.aux_ports);
+    }
+
+    /// Returns the port pair at index `pair`, if used by the Control Surface.
+    fn ports_mut(&mut self, pair: usize) -> Option<&mut midi::port::InOutManager> {
+        match pair {
+            0 => Some(&mut self.midi_ports),
+            _ => self.aux_ports.get_mut(pair - 1),
+        }
+    }
+
+    fn resize_aux_ports(&mut self, len: usize) -> anyhow::Result<()> {
+        self.aux_ports.truncate(len);
+        while self.aux_ports.len() < len {
+            let aux_ports = midi::port::InOutManager::try_new(
+                self.client_name.clone(),
+                1 + self.aux_ports.len(),
+                self.midi_tx.clone(),
+            )?;
+            self.aux_ports.push(aux_ports);
+        }
+
+        Ok(())
+    }
+
+    fn send_to_port(&mut self, pair: usize, msg: midi::Msg) {
+        let midi_ports = match pair {
+            0 => &mut self.midi_ports,
+            _ => match self.aux_ports.get_mut(pair - 1) {
+                Some(aux_ports) => aux_ports,
+                None => return,
+            },
+        };
+
+        if midi_ports.outs.is_connected() {
+            let _ = midi_ports.send(msg);
+        }
+    }
+
+    fn handle_midi_msg(&mut self, pair: usize, msg: midi::Msg) -> anyhow::Result<()> {
+        if let (0, Some(action)) = (pair, self.midi_learn) {
+            if let Some((binding, _)) = ctrl_surf::mapping::Binding::from_msg(&msg) {
+                return self.midi_learnt(action, binding);
+            }
+        }
+
         match self.ctrl_surf {
             Some(ref ctrl_surf) => {
-                let resp = ctrl_surf.lock().unwrap().msg_from_device(msg);
+                let resp = ctrl_surf.lock().unwrap().msg_from_port(pair, msg);
                 self.handle_ctrl_surf_resp(resp)
             }
             None => Ok(()),
@@ -224,15 +362,8 @@ impl Controller {
 /// Control Surface stuff.
 impl Controller {
     fn use_ctrl_surf(&mut self, ctrl_surf_name: Arc<str>) -> anyhow::Result<()> {
-        if let Some(ref ctrl_surf) = self.ctrl_surf {
-            let mut ctrl_surf = ctrl_surf.lock().unwrap();
-            if ctrl_surf.is_connected() {
-                let resp = ctrl_surf.reset();
-                drop(ctrl_surf);
-
-                let _ = self.handle_ctrl_surf_resp(resp);
-            }
-        }
+        self.disconnect_ctrl_surf();
+        self.midi_learn = None;
 
         let ctrl_surf = crate::ctrl_surf::FACTORY
             .build(&ctrl_surf_name)
@@ -243,9 +374,31 @@ impl Controller {
                 Error::UnknownControlSurface(ctrl_surf_name.clone())
             })?;
 
+        let (fader_curve, frame_rate) = {
+            let ctrl_surf_panel = self.ctrl_surf_panel.lock().unwrap();
+            (
+                ctrl_surf_panel.fader_curve(&ctrl_surf_name),
+                ctrl_surf_panel.mtc_frame_rate(),
+            )
+        };
+        {
+            // Not connected yet, so no messages.
+            let mut ctrl_surf = ctrl_surf.lock().unwrap();
+            let _ = ctrl_surf.set_fader_curve(fader_curve);
+            let _ = ctrl_surf.set_frame_rate(frame_rate);
+        }
+
+        let port_pairs = ctrl_surf.lock().unwrap().port_pairs();
+        self.resize_aux_ports(port_pairs - 1)?;
+        self.update_ports_panel();
+        self.must_repaint = true;
+
         self.ctrl_surf = Some(ctrl_surf);
+        self.ctrl_surf_tick = None;
         self.ctrl_surf_panel.lock().unwrap().update(ctrl_surf_name);
 
+        self.bind_osc()?;
+
         self.try_connect_ctrl_surf()?;
 
         Ok(())
@@ -256,15 +409,27 @@ impl Controller {
 
         for msg in resp {
             match msg {
+                ToApp(ctrl_surf::CtrlSurfEvent::Marker) => {
+                    log::debug!("Ctrl surf: Marker");
+                    self.set_marker();
+                }
+                ToApp(ctrl_surf::CtrlSurfEvent::SelectPlayerSlot(slot)) => {
+                    log::debug!("Ctrl surf: SelectPlayerSlot {slot}");
+                    self.select_player_slot(slot)?;
+                }
+                ToApp(ctrl_surf::CtrlSurfEvent::DataRequest) => {
+                    log::debug!("Ctrl surf: DataRequest");
+                    self.players
+                        .handle_event(ctrl_surf::CtrlSurfEvent::DataRequest)?;
+                    self.send_player_slot();
+                }
                 ToApp(event) => {
                     log::debug!("Ctrl surf: {event:?}");
                     self.players.handle_event(event)?;
                 }
-                ToDevice(msg) => {
-                    if self.midi_ports.are_connected() {
-                        let _ = self.midi_ports.send(msg);
-                    }
-                }
+                ToDevice(msg) => self.send_to_port(0, msg),
+                ToDeviceAt(pair, msg) => self.send_to_port(pair, msg),
+                ToOsc(client, msg) => self.send_to_osc(client, &msg),
                 ConnectionStatus(res) => {
                     use ctrl_surf::msg::ConnectionStatus::*;
                     match res {
@@ -280,6 +445,11 @@ impl Controller {
                         }
                         Result(Ok(())) => {
                             self.ctrl_surf_conn_timeout = None;
+                            self.is_ctrl_surf_lost = false;
+                            self.ctrl_surf_keepalive = Some(self.delay_event(
+                                DelayedEvent::CtrlSurfKeepalive,
+                                CTRL_SURF_KEEPALIVE_INTERVAL,
+                            ));
                             log::info!(
                                 "Connected to Control Surface {}",
                                 self.ctrl_surf_panel.lock().unwrap().cur
@@ -298,8 +468,22 @@ impl Controller {
                                 let _ = self.scan_next();
                             }
                         }
+                        Lost => {
+                            let err = Error::ControlSurfaceLost(
+                                self.ctrl_surf_panel.lock().unwrap().cur.clone(),
+                            );
+                            log::warn!("{err}");
+                            self.display_err(err);
+
+                            self.ctrl_surf_tick = None;
+                            self.is_ctrl_surf_lost = true;
+                            self.schedule_ctrl_surf_reconnect();
+                        }
                     }
                 }
+                ScheduleTick(delay) => {
+                    self.ctrl_surf_tick = Some(self.delay_event(DelayedEvent::CtrlSurfTick, delay));
+                }
             }
         }
 
@@ -320,11 +504,69 @@ impl Controller {
         }
     }
 
+    fn set_fader_curve(&mut self, curve: ctrl_surf::FaderCurve) -> anyhow::Result<()> {
+        if let Some(ref ctrl_surf) = self.ctrl_surf {
+            log::info!("Fader curve {}", curve.name());
+            let resp = ctrl_surf.lock().unwrap().set_fader_curve(curve);
+            self.handle_ctrl_surf_resp(resp)?;
+        }
+
+        Ok(())
+    }
+
+    fn set_frame_rate(&mut self, rate: midi::smpte::FrameRate) -> anyhow::Result<()> {
+        if let Some(ref ctrl_surf) = self.ctrl_surf {
+            log::info!("Frame rate {}", rate.name());
+            let resp = ctrl_surf.lock().unwrap().set_frame_rate(rate);
+            self.handle_ctrl_surf_resp(resp)?;
+        }
+
+        Ok(())
+    }
+
+    fn disconnect_ctrl_surf(&mut self) {
+        if let Some(ref ctrl_surf) = self.ctrl_surf {
+            let resp = ctrl_surf.lock().unwrap().disconnect();
+            let _ = self.handle_ctrl_surf_resp(resp);
+        }
+        self.ctrl_surf_tick = None;
+        self.ctrl_surf_keepalive = None;
+        self.is_ctrl_surf_lost = false;
+    }
+
     fn try_connect_ctrl_surf(&mut self) -> anyhow::Result<()> {
         if let Some(ref ctrl_surf) = self.ctrl_surf {
-            if !self.midi_ports.are_connected() {
+            let (uses_osc, has_handshake, is_connected) = {
+                let ctrl_surf = ctrl_surf.lock().unwrap();
+                (
+                    ctrl_surf.uses_osc(),
+                    ctrl_surf.has_handshake(),
+                    ctrl_surf.is_connected(),
+                )
+            };
+
+            if uses_osc {
+                if self.osc_socket.is_none() || is_connected {
+                    return Ok(());
+                }
+            } else if !has_handshake {
+                // Nothing to scan for: only the output port is needed.
+                if !self.midi_ports.outs.is_connected() || is_connected {
+                    return Ok(());
+                }
+            } else if !self.midi_ports.are_connected() {
                 self.start_scan();
                 return Ok(());
+            } else if let Some(pair) = self
+                .aux_ports
+                .iter()
+                .position(|ports| !ports.are_connected())
+            {
+                log::info!(
+                    "Waiting for the ports of Control Surface device {}",
+                    pair + 2
+                );
+                return Ok(());
             }
 
             log::info!(
@@ -348,6 +590,12 @@ impl Controller {
                 drop(ctrl_surf);
                 let _ = self.handle_ctrl_surf_resp(resp);
 
+                if self.is_ctrl_surf_lost {
+                    // Device still not answering, try again later.
+                    self.schedule_ctrl_surf_reconnect();
+                    return;
+                }
+
                 let err = Error::ControlSurfaceConnection(
                     self.ctrl_surf_panel.lock().unwrap().cur.clone(),
                 );
@@ -362,6 +610,64 @@ impl Controller {
         }
     }
 
+    fn ctrl_surf_keepalive(&mut self) {
+        self.ctrl_surf_keepalive = None;
+        if let Some(ref ctrl_surf) = self.ctrl_surf {
+            let resp = {
+                let mut ctrl_surf = ctrl_surf.lock().unwrap();
+                if !ctrl_surf.is_connected() {
+                    return;
+                }
+                ctrl_surf.keepalive()
+            };
+            let _ = self.handle_ctrl_surf_resp(resp);
+
+            if !self.is_ctrl_surf_lost {
+                self.ctrl_surf_keepalive = Some(self.delay_event(
+                    DelayedEvent::CtrlSurfKeepalive,
+                    CTRL_SURF_KEEPALIVE_INTERVAL,
+                ));
+            }
+        }
+    }
+
+    fn schedule_ctrl_surf_reconnect(&mut self) {
+        self.ctrl_surf_keepalive = Some(self.delay_event(
+            DelayedEvent::CtrlSurfReconnect,
+            CTRL_SURF_RECONNECT_INTERVAL,
+        ));
+    }
+
+    /// Restarts the connection after it was lost, until the device answers.
+    fn ctrl_surf_reconnect(&mut self) {
+        self.ctrl_surf_keepalive = None;
+        if !self.is_ctrl_surf_lost {
+            return;
+        }
+
+        let are_ports_connected = self.midi_ports.are_connected()
+            && self.aux_ports.iter().all(|ports| ports.are_connected());
+        if !are_ports_connected {
+            // Wait for the ports to be connected again.
+            self.schedule_ctrl_surf_reconnect();
+            return;
+        }
+
+        log::debug!("Attempting to reconnect Control Surface");
+        if let Err(err) = self.try_connect_ctrl_surf() {
+            log::debug!("Control Surface reconnection: {err}");
+            self.schedule_ctrl_surf_reconnect();
+        }
+    }
+
+    fn ctrl_surf_tick(&mut self) {
+        self.ctrl_surf_tick = None;
+        if let Some(ref ctrl_surf) = self.ctrl_surf {
+            let resp = ctrl_surf.lock().unwrap().tick();
+            let _ = self.handle_ctrl_surf_resp(resp);
+        }
+    }
+
     fn start_scan(&mut self) {
         if self
             .ctrl_surf
@@ -379,7 +685,7 @@ impl Controller {
         match self.midi_ports.scanner_next() {
             Some(port_name) => {
                 log::info!("Scanning {port_name}");
-                self.ports_panel.lock().unwrap().update(&self.midi_ports);
+                self.update_ports_panel();
                 let _ = self.try_connect_ctrl_surf();
 
                 Some(port_name)
@@ -387,7 +693,7 @@ impl Controller {
             None => {
                 let _ = self.midi_ports.disconnect(midi::port::Direction::In);
                 let _ = self.midi_ports.disconnect(midi::port::Direction::Out);
-                self.ports_panel.lock().unwrap().update(&self.midi_ports);
+                self.update_ports_panel();
                 self.must_repaint = true;
 
                 let ctrl_surf = self.ctrl_surf_panel.lock().unwrap().cur.clone();
@@ -402,6 +708,140 @@ impl Controller {
     }
 }
 
+/// OSC stuff.
+impl Controller {
+    fn ctrl_surf_uses_osc(&self) -> bool {
+        match self.ctrl_surf {
+            Some(ref ctrl_surf) => ctrl_surf.lock().unwrap().uses_osc(),
+            None => false,
+        }
+    }
+
+    /// Listens to OSC clients if the Control Surface uses OSC, stops listening otherwise.
+    fn bind_osc(&mut self) -> anyhow::Result<()> {
+        if !self.ctrl_surf_uses_osc() {
+            self.osc_socket = None;
+            return Ok(());
+        }
+
+        let addr = self.ctrl_surf_panel.lock().unwrap().osc_addr();
+        if matches!(self.osc_socket, Some(ref socket) if socket.addr() == addr) {
+            return Ok(());
+        }
+
+        // Release the previous socket before binding the new one.
+        self.osc_socket = None;
+        self.osc_socket = Some(osc::Socket::bind(addr, self.osc_tx.clone())?);
+
+        Ok(())
+    }
+
+    fn send_to_osc(&mut self, client: SocketAddr, msg: &osc::Msg) {
+        if let Some(ref osc_socket) = self.osc_socket {
+            let _ = osc_socket.send(client, msg);
+        }
+    }
+
+    fn handle_osc_msg(&mut self, client: SocketAddr, msg: osc::Msg) -> anyhow::Result<()> {
+        log::trace!("OSC msg from {client}: {msg}");
+
+        match self.ctrl_surf {
+            Some(ref ctrl_surf) => {
+                let resp = ctrl_surf.lock().unwrap().msg_from_osc(client, msg);
+                self.handle_ctrl_surf_resp(resp)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// A-B loop.
+impl Controller {
+    /// Sets the next marker at the current position & stores the result.
+    fn set_marker(&mut self) {
+        self.markers.set_next(self.position);
+        self.is_ab_seeking = false;
+        log::info!("A-B loop markers {:?}", self.markers);
+
+        self.player_panel
+            .lock()
+            .unwrap()
+            .set_markers(self.track_id.as_ref(), self.markers);
+        self.send_to_ctrl_surf(ctrl_surf::event::Data::Markers(self.markers));
+        self.must_repaint = true;
+    }
+
+    /// Loads the markers for `track_id` if it's not the current track.
+    fn load_markers(&mut self, track_id: Option<Arc<str>>) {
+        if track_id != self.track_id {
+            let mut player_panel = self.player_panel.lock().unwrap();
+            self.markers = track_id
+                .as_deref()
+                .map(|track_id| player_panel.markers(track_id))
+                .unwrap_or_default();
+            player_panel.set_markers(None, self.markers);
+            self.track_id = track_id;
+            self.is_ab_seeking = false;
+        }
+
+        // Also sent for known tracks since the Control Surface might have been reset.
+        self.send_to_ctrl_surf(ctrl_surf::event::Data::Markers(self.markers));
+    }
+
+    /// Seeks back to A when the position passes B.
+    ///
+    /// Positions past B are ignored until the seek is observed.
+    fn check_ab_loop(&mut self) -> anyhow::Result<()> {
+        if let Some((a, b)) = self.markers.range() {
+            if self.position < b {
+                self.is_ab_seeking = false;
+            } else if !self.is_ab_seeking {
+                log::debug!("A-B loop: back to {a:?}");
+                self.is_ab_seeking = true;
+                self.players
+                    .handle_event(ctrl_surf::event::Transport::SetPosition(a))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// MIDI learn.
+impl Controller {
+    /// Binds `action` to `binding` in the mapping file & rebuilds the Control Surface.
+    fn midi_learnt(
+        &mut self,
+        action: ctrl_surf::mapping::Action,
+        binding: ctrl_surf::mapping::Binding,
+    ) -> anyhow::Result<()> {
+        self.midi_learn = None;
+        self.must_repaint = true;
+
+        let res = ctrl_surf::Mapping::load().and_then(|mut mapping| {
+            mapping.set(action, binding);
+            mapping.save()
+        });
+        if let Err(err) = res {
+            self.ctrl_surf_panel
+                .lock()
+                .unwrap()
+                .midi_learnt(action, None);
+            return Err(err.into());
+        }
+
+        log::info!("MIDI learn: {} bound to {binding}", action.name());
+        let ctrl_surf_name = {
+            let mut ctrl_surf_panel = self.ctrl_surf_panel.lock().unwrap();
+            ctrl_surf_panel.midi_learnt(action, Some(binding));
+            ctrl_surf_panel.cur.clone()
+        };
+
+        // Reload the mapping.
+        self.use_ctrl_surf(ctrl_surf_name)
+    }
+}
+
 /// Mpris Player stuff.
 impl Controller {
     fn handle_mpris_event(&mut self, event: crate::mpris::Event) -> anyhow::Result<()> {
@@ -442,6 +882,9 @@ impl Controller {
             Event::Transport(Stop) => {
                 log::info!("MPRIS Player: Stop");
                 self.send_to_ctrl_surf(Stop);
+                self.track_id = None;
+                self.markers = ctrl_surf::Markers::default();
+                self.is_ab_seeking = false;
                 self.refresh_players()?;
                 {
                     let mut player_panel = self.player_panel.lock().unwrap();
@@ -469,12 +912,16 @@ impl Controller {
                 }
                 log::debug!("MPRIS Player: Track {:?} - {:?}", track.artist, track.title);
                 self.player_panel.lock().unwrap().update_track(&track);
+                let track_id = track.id.clone();
                 self.send_to_ctrl_surf(Track(track));
+                self.load_markers(track_id);
             }
             Event::Data(Position(pos)) => {
                 log::trace!("MPRIS Player: Position {pos:?}");
                 self.player_panel.lock().unwrap().update_position(pos);
                 self.send_to_ctrl_surf(Position(pos));
+                self.position = pos;
+                self.check_ab_loop()?;
                 self.must_repaint = true;
             }
             Event::Data(PlaybackStatus(status)) => {
@@ -486,6 +933,44 @@ impl Controller {
                 self.send_to_ctrl_surf(PlaybackStatus(status));
                 self.must_repaint = true;
             }
+            Event::Data(Rate(rate)) => {
+                log::debug!("MPRIS Player: Rate {rate}");
+                self.send_to_ctrl_surf(Rate(rate));
+            }
+            Event::Data(Shuffle(shuffle)) => {
+                log::debug!("MPRIS Player: Shuffle {shuffle}");
+                self.player_panel.lock().unwrap().set_shuffle(shuffle);
+                self.send_to_ctrl_surf(Shuffle(shuffle));
+                self.must_repaint = true;
+            }
+            Event::Data(LoopStatus(status)) => {
+                log::debug!("MPRIS Player: LoopStatus {status:?}");
+                self.player_panel.lock().unwrap().set_loop_status(status);
+                self.send_to_ctrl_surf(LoopStatus(status));
+                self.must_repaint = true;
+            }
+            Event::Data(data @ (Markers(_) | PlayerSlot(_))) => {
+                log::warn!("MPRIS Player: unexpected {data:?}");
+            }
+            Event::Data(Level(level)) => {
+                self.send_to_ctrl_surf(Level(level));
+            }
+            Event::Data(SystemMuted(is_muted)) => {
+                log::debug!("MPRIS Player: SystemMuted {is_muted}");
+                self.send_to_ctrl_surf(SystemMuted(is_muted));
+            }
+            Event::Data(PlayerIndex(index)) => {
+                log::debug!("MPRIS Player: PlayerIndex {index}");
+                self.send_to_ctrl_surf(PlayerIndex(index));
+            }
+            Event::Data(PlayerList(list)) => {
+                log::debug!("MPRIS Player: PlayerList {list:?}");
+                self.send_to_ctrl_surf(PlayerList(list));
+            }
+            Event::Strip(index, mixer) => {
+                log::debug!("MPRIS Player: Strip {index} {mixer:?}");
+                self.send_to_ctrl_surf(Strip(index, mixer));
+            }
             Event::PlayerSpawned(name) => {
                 log::debug!("MPRIS Player: PlayerSpawned {name:?}");
                 {
@@ -501,6 +986,7 @@ impl Controller {
                     self.players.send_all_data()?;
                 } else {
                     self.send_to_ctrl_surf(NewApp(name));
+                    self.send_player_slot();
                 }
                 self.players.unmute_system();
                 self.must_repaint = true;
@@ -524,6 +1010,10 @@ impl Controller {
                 }
                 self.must_repaint = true;
             }
+            Event::Mixer(Balance(balance)) => {
+                log::debug!("MPRIS Player: Balance {balance:.2}");
+                self.send_to_ctrl_surf(Balance(balance));
+            }
             Event::Mixer(Mute) => {
                 log::debug!("MPRIS Player: Mute");
                 self.player_panel.lock().unwrap().set_muted(true);
@@ -541,15 +1031,51 @@ impl Controller {
         Ok(())
     }
 
+    /// Polls the volume of the players which are not the current one.
+    fn poll_strips(&mut self) {
+        self.strips_poll = Some(self.delay_event(DelayedEvent::StripsPoll, STRIPS_POLL_INTERVAL));
+
+        let is_connected = self
+            .ctrl_surf
+            .as_ref()
+            .is_some_and(|cs| cs.lock().unwrap().is_connected());
+        if is_connected {
+            if let Err(err) = self.players.poll_strips() {
+                log::debug!("Polling strips: {err}");
+            }
+        }
+    }
+
     fn refresh_players(&mut self) -> anyhow::Result<()> {
         self.players.refresh()?;
         self.player_panel
             .lock()
             .unwrap()
             .update_players(&self.players);
+        self.send_player_slot();
 
         Ok(())
     }
+
+    /// Makes the player assigned to `slot` the current one.
+    fn select_player_slot(&mut self, slot: usize) -> anyhow::Result<()> {
+        let name = self.player_panel.lock().unwrap().slot_player(slot);
+        match name {
+            Some(name) if self.players.list().any(|n| n == name) => {
+                log::info!("Selecting player {name} from slot {}", slot + 1);
+                self.players.set_cur(name)?;
+            }
+            Some(name) => log::debug!("Player {name} from slot {} not available", slot + 1),
+            None => log::debug!("No player assigned to slot {}", slot + 1),
+        }
+
+        Ok(())
+    }
+
+    fn send_player_slot(&mut self) {
+        let slot = self.player_panel.lock().unwrap().cur_slot();
+        self.send_to_ctrl_surf(ctrl_surf::event::Data::PlayerSlot(slot));
+    }
 }
 
 /// Controller loop.
@@ -558,9 +1084,12 @@ impl Controller {
         mut self,
         req_rx: channel::Receiver<app::Request>,
         player_rx: channel::Receiver<mpris::Event>,
-        midi_rx: channel::Receiver<midi::Msg>,
+        midi_rx: channel::Receiver<(usize, midi::Msg)>,
+        osc_rx: channel::Receiver<(SocketAddr, osc::Msg)>,
         delayed_evt_rx: channel::Receiver<DelayedEvent>,
     ) {
+        self.poll_strips();
+
         loop {
             channel::select! {
                 recv(req_rx) -> request => {
@@ -590,7 +1119,7 @@ impl Controller {
                 }
                 recv(midi_rx) -> midi_msg => {
                     match midi_msg {
-                        Ok(midi_msg) => match self.handle_midi_msg(midi_msg) {
+                        Ok((pair, midi_msg)) => match self.handle_midi_msg(pair, midi_msg) {
                             Ok(()) => (),
                             Err(err) => self.display_err(err),
                         },
@@ -600,6 +1129,18 @@ impl Controller {
                         }
                     }
                 }
+                recv(osc_rx) -> osc_msg => {
+                    match osc_msg {
+                        Ok((client, osc_msg)) => match self.handle_osc_msg(client, osc_msg) {
+                            Ok(()) => (),
+                            Err(err) => self.display_err(err),
+                        },
+                        Err(err) => {
+                            log::error!("Error OSC msg channel: {err}");
+                            break;
+                        }
+                    }
+                }
                 recv(delayed_evt_rx) -> devt => {
                     use DelayedEvent::*;
                     match devt {
@@ -607,9 +1148,13 @@ impl Controller {
                             CtrlSurfConnectionTimeout => {
                                 self.ctrl_surf_connection_timeout();
                             }
+                            CtrlSurfTick => self.ctrl_surf_tick(),
+                            CtrlSurfKeepalive => self.ctrl_surf_keepalive(),
+                            CtrlSurfReconnect => self.ctrl_surf_reconnect(),
                             TrackMetaRetry => {
                                 let _ = self.players.send_track_meta();
                             }
+                            StripsPoll => self.poll_strips(),
                         },
                         Err(err) => {
                             log::error!("Error delayed event channel: {err}");
@@ -1,34 +1,125 @@
 use eframe::egui;
 use once_cell::sync::Lazy;
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    sync::Arc,
+};
+
+use crate::{
+    ctrl_surf::{
+        mapping::{Action, Binding},
+        FaderCurve, Mapping, GENERIC_MIDI, MMC, MTC, OSC,
+    },
+    midi::smpte::FrameRate,
+};
 
 #[derive(Debug)]
 pub enum Response {
     Use(Arc<str>),
     Unuse,
     Scan,
+    FaderCurve(FaderCurve),
+    /// Binds the action to the next MIDI message, `None` cancels.
+    MidiLearn(Option<Action>),
+    OscAddr(SocketAddr),
+    MtcFrameRate(FrameRate),
 }
 
 static NO_CTRL_SURF: Lazy<Arc<str>> = Lazy::new(|| "No Control Surface".into());
 const STORAGE_CTRL_SURF: &str = "control_surface";
+/// Lines of Control Surface name & fader curve name separated by a tab.
+const STORAGE_FADER_CURVES: &str = "fader_curves";
+const STORAGE_OSC_ADDR: &str = "osc_addr";
+/// Only local clients are accepted by default.
+const DEFAULT_OSC_ADDR: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 8000));
+const STORAGE_MTC_FRAME_RATE: &str = "mtc_frame_rate";
 
 pub struct ControlSurfacePanel {
     pub list: Vec<Arc<str>>,
     pub cur: Arc<str>,
+    fader_curves: BTreeMap<Arc<str>, FaderCurve>,
+    mapping: Mapping,
+    learn_action: Action,
+    is_learning: bool,
+    osc_addr: SocketAddr,
+    osc_addr_str: String,
+    mtc_frame_rate: FrameRate,
 }
 
 impl ControlSurfacePanel {
-    pub fn new() -> Self {
+    pub fn new(storage: Option<&dyn eframe::Storage>) -> Self {
         let mut list: Vec<Arc<str>> = crate::ctrl_surf::FACTORY.list().map(Arc::from).collect();
         list.sort();
 
         let cur = NO_CTRL_SURF.clone();
 
-        Self { list, cur }
+        let mut fader_curves = BTreeMap::new();
+        if let Some(fader_curves_str) =
+            storage.and_then(|storage| storage.get_string(STORAGE_FADER_CURVES))
+        {
+            for line in fader_curves_str.lines() {
+                let curve = line
+                    .split_once('\t')
+                    .and_then(|(name, curve)| Some((name, FaderCurve::from_name(curve)?)));
+                match curve {
+                    Some((name, curve)) => {
+                        fader_curves.insert(name.into(), curve);
+                    }
+                    None => log::warn!("Ignoring stored fader curve {line}"),
+                }
+            }
+        }
+
+        let osc_addr = storage
+            .and_then(|storage| storage.get_string(STORAGE_OSC_ADDR))
+            .and_then(|addr| addr.parse().ok())
+            .unwrap_or(DEFAULT_OSC_ADDR);
+
+        let mtc_frame_rate = storage
+            .and_then(|storage| storage.get_string(STORAGE_MTC_FRAME_RATE))
+            .and_then(|rate| FrameRate::from_name(&rate))
+            .unwrap_or_default();
+
+        Self {
+            list,
+            cur,
+            fader_curves,
+            mapping: Mapping::load().unwrap_or_default(),
+            learn_action: Action::PlayPause,
+            is_learning: false,
+            osc_addr,
+            osc_addr_str: osc_addr.to_string(),
+            mtc_frame_rate,
+        }
+    }
+
+    /// Returns the fader curve selected for the Control Surface `name`.
+    pub fn fader_curve(&self, name: &str) -> FaderCurve {
+        self.fader_curves.get(name).copied().unwrap_or_default()
+    }
+
+    /// Returns the UDP address to listen to for OSC messages.
+    pub fn osc_addr(&self) -> SocketAddr {
+        self.osc_addr
+    }
+
+    pub fn mtc_frame_rate(&self) -> FrameRate {
+        self.mtc_frame_rate
     }
 
     #[must_use]
     pub fn show(&mut self, ui: &mut egui::Ui) -> Option<Response> {
+        let resp = self.show_ctrl_surf(ui);
+        match self.cur.as_ref() {
+            GENERIC_MIDI => self.show_midi_learn(ui).or(resp),
+            OSC => self.show_osc(ui).or(resp),
+            MMC | MTC => self.show_mtc(ui).or(resp),
+            _ => resp,
+        }
+    }
+
+    fn show_ctrl_surf(&mut self, ui: &mut egui::Ui) -> Option<Response> {
         ui.horizontal(|ui| {
             use Response::*;
 
@@ -65,6 +156,119 @@ impl ControlSurfacePanel {
                 }
             });
 
+            if self.cur != *NO_CTRL_SURF {
+                ui.add_space(20f32);
+                let mut fader_curve = self.fader_curve(&self.cur);
+                egui::ComboBox::from_label("Fader curve")
+                    .selected_text(fader_curve.name())
+                    .show_ui(ui, |ui| {
+                        for curve in crate::ctrl_surf::FaderCurve::ALL {
+                            if ui
+                                .selectable_value(&mut fader_curve, curve, curve.name())
+                                .clicked()
+                            {
+                                self.fader_curves.insert(self.cur.clone(), curve);
+                                resp = Some(FaderCurve(curve));
+                            }
+                        }
+                    });
+            }
+
+            if matches!(resp, Some(Use(_) | Unuse)) {
+                self.is_learning = false;
+            }
+
+            resp
+        })
+        .inner
+    }
+
+    fn show_midi_learn(&mut self, ui: &mut egui::Ui) -> Option<Response> {
+        ui.horizontal(|ui| {
+            use Response::*;
+
+            let mut resp = None;
+
+            ui.add_enabled_ui(!self.is_learning, |ui| {
+                egui::ComboBox::from_label("Action")
+                    .selected_text(self.learn_action.name())
+                    .show_ui(ui, |ui| {
+                        for action in Action::ALL {
+                            ui.selectable_value(&mut self.learn_action, action, action.name());
+                        }
+                    });
+            });
+
+            ui.add_space(20f32);
+            if self.is_learning {
+                ui.label("Waiting for a MIDI message...");
+                if ui.button("Cancel").clicked() {
+                    self.is_learning = false;
+                    resp = Some(MidiLearn(None));
+                }
+            } else {
+                match self.mapping.get(self.learn_action) {
+                    Some(binding) => ui.monospace(binding.to_string()),
+                    None => ui.label("Not mapped"),
+                };
+
+                let learn_btn = ui
+                    .button("Learn")
+                    .on_hover_text(Mapping::path().display().to_string());
+                if learn_btn.clicked() {
+                    self.is_learning = true;
+                    resp = Some(MidiLearn(Some(self.learn_action)));
+                }
+            }
+
+            resp
+        })
+        .inner
+    }
+
+    fn show_osc(&mut self, ui: &mut egui::Ui) -> Option<Response> {
+        ui.horizontal(|ui| {
+            ui.label("OSC UDP address");
+            let addr_edit =
+                ui.add(egui::TextEdit::singleline(&mut self.osc_addr_str).desired_width(160f32));
+            if !addr_edit.lost_focus() {
+                return None;
+            }
+
+            match self.osc_addr_str.trim().parse::<SocketAddr>() {
+                Ok(addr) if addr.port() > 0 => {
+                    if addr == self.osc_addr {
+                        return None;
+                    }
+                    self.osc_addr = addr;
+                    Some(Response::OscAddr(addr))
+                }
+                _ => {
+                    self.osc_addr_str = self.osc_addr.to_string();
+                    None
+                }
+            }
+        })
+        .inner
+    }
+
+    fn show_mtc(&mut self, ui: &mut egui::Ui) -> Option<Response> {
+        ui.horizontal(|ui| {
+            let mut resp = None;
+
+            egui::ComboBox::from_label("Frame rate")
+                .selected_text(self.mtc_frame_rate.name())
+                .show_ui(ui, |ui| {
+                    for rate in FrameRate::ALL {
+                        if ui
+                            .selectable_value(&mut self.mtc_frame_rate, rate, rate.name())
+                            .clicked()
+                        {
+                            resp = Some(Response::MtcFrameRate(rate));
+                        }
+                    }
+                });
+
             resp
         })
         .inner
@@ -88,10 +292,31 @@ impl ControlSurfacePanel {
 
     pub fn save(&self, storage: &mut dyn eframe::Storage) {
         storage.set_string(STORAGE_CTRL_SURF, self.cur.to_string());
+
+        let fader_curves = self
+            .fader_curves
+            .iter()
+            .map(|(name, curve)| format!("{name}\t{}", curve.name()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        storage.set_string(STORAGE_FADER_CURVES, fader_curves);
+        storage.set_string(STORAGE_OSC_ADDR, self.osc_addr.to_string());
+        storage.set_string(
+            STORAGE_MTC_FRAME_RATE,
+            self.mtc_frame_rate.name().to_string(),
+        );
     }
 }
 
 impl ControlSurfacePanel {
+    /// Ends MIDI learn, `binding` being the newly learnt binding if any.
+    pub fn midi_learnt(&mut self, action: Action, binding: Option<Binding>) {
+        self.is_learning = false;
+        if let Some(binding) = binding {
+            self.mapping.set(action, binding);
+        }
+    }
+
     pub fn update(&mut self, ctrl_surf: impl Into<Option<Arc<str>>>) {
         self.cur = ctrl_surf.into().unwrap_or_else(|| NO_CTRL_SURF.clone());
     }
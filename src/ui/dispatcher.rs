@@ -19,6 +19,18 @@ impl Dispatcher<super::ControlSurfacePanel> {
                 Scan => {
                     app.send_req(Request::ScanControlSurface);
                 }
+                FaderCurve(curve) => {
+                    app.send_req(Request::FaderCurve(curve));
+                }
+                MidiLearn(action) => {
+                    app.send_req(Request::MidiLearn(action));
+                }
+                OscAddr(addr) => {
+                    app.send_req(Request::OscAddr(addr));
+                }
+                MtcFrameRate(rate) => {
+                    app.send_req(Request::MtcFrameRate(rate));
+                }
             }
         }
     }
@@ -33,11 +45,11 @@ impl Dispatcher<super::PortsPanel> {
             app.send_req(Request::RefreshPorts);
 
             match resp {
-                Connect((direction, port_name)) => {
-                    app.send_req(Request::ConnectPort((direction, port_name)));
+                Connect((pair, direction, port_name)) => {
+                    app.send_req(Request::ConnectPort((pair, direction, port_name)));
                 }
-                Disconnect(direction) => {
-                    app.send_req(Request::DisconnectPort(direction));
+                Disconnect((pair, direction)) => {
+                    app.send_req(Request::DisconnectPort((pair, direction)));
                 }
                 CheckingList => (), // only refresh ports & clear last_err
             }
@@ -69,12 +81,16 @@ impl From<player::Response> for Request {
         match resp {
             Use(player_name) => Request::UsePlayer(player_name),
             CheckingList => Request::RefreshPlayers,
+            SeekStep(seek_step) => Request::SeekStep(seek_step),
             Position(pos) => Transport::SetPosition(pos).into(),
             Mute => Mixer::Mute.into(),
             UnMute => Mixer::Unmute.into(),
             PlayPause => Transport::PlayPause.into(),
             Previous => Transport::Previous.into(),
             Next => Transport::Next.into(),
+            Shuffle(shuffle) => Transport::SetShuffle(shuffle).into(),
+            Loop(status) => Transport::SetLoopStatus(status).into(),
+            PlayerSlot(slot) => Request::PlayerSlot(slot),
         }
     }
 }
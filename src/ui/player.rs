@@ -1,24 +1,35 @@
 use eframe::egui;
 use once_cell::sync::Lazy;
-use std::{sync::Arc, time::Duration};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 use crate::{
-    ctrl_surf::{self, Timecode},
+    ctrl_surf::{self, LoopStatus, Markers, Timecode},
     mpris,
 };
 
 static NO_PLAYER: Lazy<Arc<str>> = Lazy::new(|| "No Player".into());
 const STORAGE_PLAYER: &str = "player";
+const STORAGE_SEEK_STEP: &str = "seek_step";
+/// Lines of A & B positions in seconds and track id separated by tabs.
+const STORAGE_MARKERS: &str = "markers";
+/// Player names by slot, one per line, empty for a free slot.
+const STORAGE_PLAYER_SLOTS: &str = "player_slots";
+
+const PLAYER_SLOTS: usize = 8;
 
 pub enum Response {
     Use(Arc<str>),
     CheckingList,
+    SeekStep(Duration),
     Position(Duration),
     Mute,
     UnMute,
     PlayPause,
     Previous,
     Next,
+    Shuffle(bool),
+    Loop(LoopStatus),
+    PlayerSlot(Option<usize>),
 }
 
 pub struct PlayerPanel {
@@ -27,6 +38,8 @@ pub struct PlayerPanel {
     caps: mpris::Caps,
     is_playing: bool,
     is_muted: bool,
+    shuffle: bool,
+    loop_status: LoopStatus,
     artist: Option<Arc<str>>,
     album: Option<Arc<str>>,
     title: Option<Arc<str>>,
@@ -35,6 +48,13 @@ pub struct PlayerPanel {
     duration: Duration,
     duration_str: Option<String>,
     is_pending_seek: bool,
+    seek_step: Duration,
+    /// A-B loop markers of the current track.
+    cur_markers: Markers,
+    /// A-B loop markers by track id.
+    markers: BTreeMap<Arc<str>, Markers>,
+    /// Players assigned to the slots selectable from the Control Surface.
+    slots: [Option<Arc<str>>; PLAYER_SLOTS],
     texture: Option<(Arc<str>, egui::TextureHandle)>,
     egui_ctx: egui::Context,
 }
@@ -47,6 +67,8 @@ impl PlayerPanel {
             caps: mpris::Caps::empty(),
             is_playing: false,
             is_muted: false,
+            shuffle: false,
+            loop_status: LoopStatus::None,
             artist: None,
             album: None,
             title: None,
@@ -55,11 +77,60 @@ impl PlayerPanel {
             duration: Duration::ZERO,
             duration_str: None,
             is_pending_seek: false,
+            seek_step: mpris::DEFAULT_SEEK_STEP,
+            cur_markers: Markers::default(),
+            markers: cc
+                .storage
+                .and_then(|storage| storage.get_string(STORAGE_MARKERS))
+                .map(|markers| Self::parse_markers(&markers))
+                .unwrap_or_default(),
+            slots: cc
+                .storage
+                .and_then(|storage| storage.get_string(STORAGE_PLAYER_SLOTS))
+                .map(|slots| Self::parse_slots(&slots))
+                .unwrap_or_default(),
             texture: None,
             egui_ctx: cc.egui_ctx.clone(),
         }
     }
 
+    fn parse_markers(markers_str: &str) -> BTreeMap<Arc<str>, Markers> {
+        fn parse_pos(pos: &str) -> Option<Option<Duration>> {
+            if pos.is_empty() {
+                return Some(None);
+            }
+
+            let secs = pos.parse::<f64>().ok()?;
+            Duration::try_from_secs_f64(secs).ok().map(Some)
+        }
+
+        let mut markers = BTreeMap::new();
+        for line in markers_str.lines() {
+            let mut fields = line.splitn(3, '\t');
+            let a = fields.next().and_then(parse_pos);
+            let b = fields.next().and_then(parse_pos);
+            match (a, b, fields.next()) {
+                (Some(a), Some(b), Some(track_id)) => {
+                    markers.insert(Arc::from(track_id), Markers { a, b });
+                }
+                _ => log::warn!("Ignoring stored markers {line}"),
+            }
+        }
+
+        markers
+    }
+
+    fn parse_slots(slots_str: &str) -> [Option<Arc<str>>; PLAYER_SLOTS] {
+        let mut slots: [Option<Arc<str>>; PLAYER_SLOTS] = Default::default();
+        for (slot, name) in slots.iter_mut().zip(slots_str.lines()) {
+            if !name.is_empty() {
+                *slot = Some(name.into());
+            }
+        }
+
+        slots
+    }
+
     pub fn show(&mut self, ui: &mut egui::Ui) -> Option<Response> {
         use Response::*;
 
@@ -71,28 +142,77 @@ impl PlayerPanel {
             .frame(egui::Frame::default().inner_margin(margin))
             .show_separator_line(false)
             .show_inside(ui, |ui| {
-                let player_resp = egui::ComboBox::from_label("Player")
-                    .selected_text(self.cur.as_ref())
-                    .show_ui(ui, |ui| {
-                        let mut resp = None;
-                        for player in self.list.iter() {
-                            if ui
-                                .selectable_value(&mut self.cur, player.clone(), player.as_ref())
-                                .clicked()
-                            {
-                                resp = Some(Use(player.clone()));
+                ui.horizontal(|ui| {
+                    let player_resp = egui::ComboBox::from_label("Player")
+                        .selected_text(self.cur.as_ref())
+                        .show_ui(ui, |ui| {
+                            let mut resp = None;
+                            for player in self.list.iter() {
+                                if ui
+                                    .selectable_value(
+                                        &mut self.cur,
+                                        player.clone(),
+                                        player.as_ref(),
+                                    )
+                                    .clicked()
+                                {
+                                    resp = Some(Use(player.clone()));
+                                }
                             }
-                        }
 
-                        resp
-                    })
-                    .inner;
+                            resp
+                        })
+                        .inner;
+
+                    if let Some(None) = player_resp {
+                        resp = Some(CheckingList);
+                    } else {
+                        resp = player_resp.flatten();
+                    }
+
+                    ui.add_space(20f32);
+
+                    let cur_slot = self.cur_slot();
+                    ui.add_enabled_ui(self.cur != *NO_PLAYER, |ui| {
+                        egui::ComboBox::from_label("Slot")
+                            .selected_text(cur_slot.map_or("-".to_string(), slot_name))
+                            .show_ui(ui, |ui| {
+                                if ui.selectable_label(cur_slot.is_none(), "-").clicked() {
+                                    self.set_cur_slot(None);
+                                    resp = Some(PlayerSlot(None));
+                                }
+                                for slot in 0..PLAYER_SLOTS {
+                                    let text = match self.slots[slot] {
+                                        Some(ref name) if *name != self.cur => {
+                                            format!("{} ({name})", slot_name(slot))
+                                        }
+                                        _ => slot_name(slot),
+                                    };
+                                    if ui.selectable_label(cur_slot == Some(slot), text).clicked() {
+                                        self.set_cur_slot(Some(slot));
+                                        resp = Some(PlayerSlot(Some(slot)));
+                                    }
+                                }
+                            });
+                    });
 
-                if let Some(None) = player_resp {
-                    resp = Some(CheckingList);
-                } else {
-                    resp = player_resp.flatten();
-                }
+                    ui.add_space(20f32);
+
+                    let mut seek_step = self.seek_step.as_secs_f64();
+                    let seek_step_resp = ui.add(
+                        egui::DragValue::new(&mut seek_step)
+                            .clamp_range(0.1..=60.0)
+                            .speed(0.1)
+                            .fixed_decimals(1)
+                            .suffix(" s"),
+                    );
+                    ui.label("Seek step");
+
+                    if seek_step_resp.changed() {
+                        self.seek_step = Duration::from_secs_f64(seek_step);
+                        resp = Some(SeekStep(self.seek_step));
+                    }
+                });
             });
 
         margin.bottom = 0.0;
@@ -119,6 +239,13 @@ impl PlayerPanel {
                                     self.duration_str.as_ref().map_or("--:--", String::as_str),
                                 ));
 
+                                if let Some(a) = self.cur_markers.a {
+                                    ui.monospace(format!("A {}", Timecode::from(a)));
+                                }
+                                if let Some(b) = self.cur_markers.b {
+                                    ui.monospace(format!("B {}", Timecode::from(b)));
+                                }
+
                                 #[cfg(feature = "pulsectl")]
                                 let can_mute = true;
                                 #[cfg(not(feature = "pulsectl"))]
@@ -161,6 +288,23 @@ impl PlayerPanel {
                                         resp = Some(Next);
                                     }
                                 });
+
+                                ui.add_enabled_ui(self.caps.contains(Caps::SHUFFLE), |ui| {
+                                    if ui.selectable_label(self.shuffle, "🔀").clicked() {
+                                        resp = Some(Shuffle(!self.shuffle));
+                                    }
+                                });
+
+                                ui.add_enabled_ui(self.caps.contains(Caps::LOOP), |ui| {
+                                    let (is_looping, text) = match self.loop_status {
+                                        LoopStatus::None => (false, "🔁"),
+                                        LoopStatus::Track => (true, "🔂"),
+                                        LoopStatus::Playlist => (true, "🔁"),
+                                    };
+                                    if ui.selectable_label(is_looping, text).clicked() {
+                                        resp = Some(Loop(self.loop_status.next()));
+                                    }
+                                });
                             });
                         });
 
@@ -251,28 +395,61 @@ impl PlayerPanel {
         resp
     }
 
-    pub fn setup(storage: Option<&dyn eframe::Storage>) -> Option<Response> {
+    pub fn setup(storage: Option<&dyn eframe::Storage>) -> impl Iterator<Item = Response> {
         use Response::*;
 
+        let mut resp = Vec::new();
         if let Some(storage) = storage {
+            if let Some(seek_step) = storage.get_string(STORAGE_SEEK_STEP) {
+                match seek_step.parse::<f64>() {
+                    Ok(secs) => match Duration::try_from_secs_f64(secs) {
+                        Ok(step) if !step.is_zero() => resp.push(SeekStep(step)),
+                        _ => log::warn!("Invalid stored seek step {seek_step}"),
+                    },
+                    Err(err) => log::warn!("Invalid stored seek step {seek_step}: {err}"),
+                }
+            }
             if let Some(player) = storage.get_string(STORAGE_PLAYER) {
-                return Some(Use(player.into()));
+                resp.push(Use(player.into()));
             }
         }
 
-        None
+        resp.into_iter()
     }
 
     pub fn save(&self, storage: &mut dyn eframe::Storage) {
         if self.cur != *NO_PLAYER {
             storage.set_string(STORAGE_PLAYER, self.cur.to_string());
         }
+        storage.set_string(STORAGE_SEEK_STEP, self.seek_step.as_secs_f64().to_string());
+
+        let pos_str = |pos: Option<Duration>| {
+            pos.map(|pos| pos.as_secs_f64().to_string())
+                .unwrap_or_default()
+        };
+        let markers = self
+            .markers
+            .iter()
+            .map(|(track_id, markers)| {
+                format!("{}\t{}\t{track_id}", pos_str(markers.a), pos_str(markers.b))
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        storage.set_string(STORAGE_MARKERS, markers);
+
+        let slots = self
+            .slots
+            .iter()
+            .map(|name| name.as_deref().unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join("\n");
+        storage.set_string(STORAGE_PLAYER_SLOTS, slots);
     }
 }
 
 impl PlayerPanel {
     pub fn update_players(&mut self, players: &mpris::Players) {
-        self.list.clear();
+        let prev_list = std::mem::take(&mut self.list);
 
         self.list.extend(players.list());
         self.list.sort();
@@ -284,6 +461,49 @@ impl PlayerPanel {
             assert!(self.list.is_empty());
             self.cur = NO_PLAYER.clone();
         }
+
+        // Assign the players which just appeared to the first free slots,
+        // so that the players whose slot was freed are left out.
+        for name in self.list.iter() {
+            if prev_list.contains(name) || self.slots.iter().flatten().any(|slot| slot == name) {
+                continue;
+            }
+
+            if let Some(slot) = self.slots.iter_mut().find(|slot| slot.is_none()) {
+                *slot = Some(name.clone());
+            }
+        }
+    }
+
+    /// Returns the player assigned to `slot`.
+    pub fn slot_player(&self, slot: usize) -> Option<Arc<str>> {
+        self.slots.get(slot).cloned().flatten()
+    }
+
+    /// Returns the slot of the current player.
+    pub fn cur_slot(&self) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|name| name.as_ref() == Some(&self.cur))
+    }
+
+    /// Assigns the current player to `slot`, or frees its slot if `None`.
+    ///
+    /// The player previously assigned to `slot` takes the former
+    /// slot of the current player.
+    fn set_cur_slot(&mut self, slot: Option<usize>) {
+        let prev_name = slot.and_then(|slot| self.slots[slot].take());
+        if let Some(cur_slot) = self.cur_slot() {
+            self.slots[cur_slot] = prev_name;
+        }
+
+        if let Some(slot) = slot {
+            self.slots[slot] = Some(self.cur.clone());
+        }
+    }
+
+    pub fn set_seek_step(&mut self, seek_step: Duration) {
+        self.seek_step = seek_step;
     }
 
     pub fn set_caps(&mut self, caps: mpris::Caps) {
@@ -365,10 +585,38 @@ impl PlayerPanel {
         self.is_muted = is_muted;
     }
 
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        self.shuffle = shuffle;
+    }
+
+    pub fn set_loop_status(&mut self, status: LoopStatus) {
+        self.loop_status = status;
+    }
+
+    /// Returns the stored A-B loop markers for `track_id`.
+    pub fn markers(&self, track_id: &str) -> Markers {
+        self.markers.get(track_id).copied().unwrap_or_default()
+    }
+
+    /// Sets the A-B loop markers of the current track & stores them for `track_id`.
+    pub fn set_markers(&mut self, track_id: Option<&Arc<str>>, markers: Markers) {
+        self.cur_markers = markers;
+
+        if let Some(track_id) = track_id {
+            if markers.is_empty() {
+                self.markers.remove(track_id);
+            } else {
+                self.markers.insert(track_id.clone(), markers);
+            }
+        }
+    }
+
     pub fn reset(&mut self) {
         self.caps = mpris::Caps::empty();
         self.is_playing = false;
         self.is_muted = false;
+        self.shuffle = false;
+        self.loop_status = LoopStatus::None;
         self.artist = None;
         self.album = None;
         self.title = None;
@@ -377,6 +625,11 @@ impl PlayerPanel {
         self.duration = Duration::ZERO;
         self.duration_str = None;
         self.is_pending_seek = false;
+        self.cur_markers = Markers::default();
         self.texture = None;
     }
 }
+
+fn slot_name(slot: usize) -> String {
+    format!("F{}", slot + 1)
+}
@@ -7,6 +7,8 @@ use crate::midi::{self, port::Direction};
 static DISCONNECTED: Lazy<Arc<str>> = Lazy::new(|| "Disconnected".into());
 const STORAGE_PORT_IN: &str = "port_in";
 const STORAGE_PORT_OUT: &str = "port_out";
+/// In & Out port names for the additional port pairs, one per line.
+const STORAGE_AUX_PORTS: &str = "aux_ports";
 
 #[derive(Debug)]
 pub struct DirectionalPorts {
@@ -35,31 +37,43 @@ impl Default for DirectionalPorts {
     }
 }
 
+/// Port pair index, starting with 0 for the main port pair.
+pub type PairIndex = usize;
+
 #[derive(Debug)]
 pub enum Response {
-    Connect((Direction, Arc<str>)),
-    Disconnect(Direction),
+    Connect((PairIndex, Direction, Arc<str>)),
+    Disconnect((PairIndex, Direction)),
     CheckingList,
 }
 
 pub struct PortsPanel {
-    ports: [DirectionalPorts; 2],
+    pairs: Vec<[DirectionalPorts; 2]>,
 }
 
 impl PortsPanel {
     pub fn new() -> Self {
         PortsPanel {
-            ports: [DirectionalPorts::default(), DirectionalPorts::default()],
+            pairs: vec![Default::default()],
         }
     }
 
+    pub fn pairs(&self) -> usize {
+        self.pairs.len()
+    }
+
     #[must_use]
-    pub fn show(&mut self, direction: Direction, ui: &mut egui::Ui) -> Option<Response> {
+    pub fn show(
+        &mut self,
+        pair: PairIndex,
+        direction: Direction,
+        ui: &mut egui::Ui,
+    ) -> Option<Response> {
         use Response::*;
 
-        let dir_port = &mut self.ports[direction.idx()];
+        let dir_port = &mut self.pairs[pair][direction.idx()];
 
-        let resp = egui::ComboBox::from_label(direction.as_str())
+        let resp = egui::ComboBox::new((pair, direction.idx()), direction.as_str())
             .selected_text(dir_port.cur.as_ref())
             .show_ui(ui, |ui| {
                 let mut resp = None;
@@ -72,7 +86,7 @@ impl PortsPanel {
                     )
                     .clicked()
                 {
-                    resp = Some(Disconnect(direction));
+                    resp = Some(Disconnect((pair, direction)));
                 }
 
                 for port in dir_port.list.iter() {
@@ -80,7 +94,7 @@ impl PortsPanel {
                         .selectable_value(&mut dir_port.cur, port.clone(), port.as_ref())
                         .clicked()
                     {
-                        resp = Some(Connect((direction, port.clone())));
+                        resp = Some(Connect((pair, direction, port.clone())));
                     }
                 }
 
@@ -102,12 +116,20 @@ impl PortsPanel {
         if let Some(storage) = storage {
             if let Some(port) = storage.get_string(STORAGE_PORT_IN) {
                 if port != DISCONNECTED.as_ref() {
-                    resp.push(Connect((Direction::In, port.into())));
+                    resp.push(Connect((0, Direction::In, port.into())));
                 }
             }
             if let Some(port) = storage.get_string(STORAGE_PORT_OUT) {
                 if port != DISCONNECTED.as_ref() {
-                    resp.push(Connect((Direction::Out, port.into())));
+                    resp.push(Connect((0, Direction::Out, port.into())));
+                }
+            }
+            if let Some(aux_ports) = storage.get_string(STORAGE_AUX_PORTS) {
+                let directions = [Direction::In, Direction::Out].into_iter().cycle();
+                for (idx, (port, direction)) in aux_ports.lines().zip(directions).enumerate() {
+                    if port != DISCONNECTED.as_ref() {
+                        resp.push(Connect((1 + idx / 2, direction, port.into())));
+                    }
                 }
             }
         }
@@ -118,20 +140,40 @@ impl PortsPanel {
     pub fn save(&self, storage: &mut dyn eframe::Storage) {
         storage.set_string(
             STORAGE_PORT_IN,
-            self.ports[Direction::In.idx()].cur.to_string(),
+            self.pairs[0][Direction::In.idx()].cur.to_string(),
         );
         storage.set_string(
             STORAGE_PORT_OUT,
-            self.ports[Direction::Out.idx()].cur.to_string(),
+            self.pairs[0][Direction::Out.idx()].cur.to_string(),
         );
+
+        let aux_ports = self.pairs[1..]
+            .iter()
+            .flat_map(|pair| pair.iter().map(|dir_ports| dir_ports.cur.as_ref()))
+            .collect::<Vec<&str>>()
+            .join("\n");
+        storage.set_string(STORAGE_AUX_PORTS, aux_ports);
     }
 }
 
 /// The following functions must be called from the AppController thread,
 /// not the UI update thread.
 impl PortsPanel {
-    pub fn update(&mut self, midi_ports: &midi::port::InOutManager) {
-        self.ports[Direction::In.idx()].update_from(&midi_ports.ins);
-        self.ports[Direction::Out.idx()].update_from(&midi_ports.outs);
+    pub fn update(
+        &mut self,
+        midi_ports: &midi::port::InOutManager,
+        aux_ports: &[midi::port::InOutManager],
+    ) {
+        self.pairs
+            .resize_with(1 + aux_ports.len(), Default::default);
+
+        for (pair, midi_ports) in self
+            .pairs
+            .iter_mut()
+            .zip(std::iter::once(midi_ports).chain(aux_ports))
+        {
+            pair[Direction::In.idx()].update_from(&midi_ports.ins);
+            pair[Direction::Out.idx()].update_from(&midi_ports.outs);
+        }
     }
 }